@@ -0,0 +1,173 @@
+// SPDX-License-Identifier: Apache-2.0
+
+// TODO: Replace with a VAST API call once comments are supported.
+
+use indexmap::IndexMap;
+
+#[derive(Default)]
+pub struct CommentInfo {
+    /// Map from module definition name to the comment placed before its
+    /// declaration.
+    pub modules: IndexMap<String, String>,
+    /// Map from module definition name to instance name to the comment placed
+    /// before that instance.
+    pub instances: IndexMap<String, IndexMap<String, String>>,
+}
+
+pub fn add_module_comments(text: String, comments: &IndexMap<String, String>) -> String {
+    let mut lines: Vec<String> = Vec::new();
+    for line in text.split('\n') {
+        if let Some(rest) = line.strip_prefix("module ") {
+            let def_name = rest.split(['(', ';', ' ']).next().unwrap();
+            if let Some(comment) = comments.get(def_name) {
+                lines.push("/*".to_string());
+                for comment_line in comment.lines() {
+                    lines.push(format!(" * {}", comment_line).trim_end().to_string());
+                }
+                lines.push(" */".to_string());
+            }
+        }
+        lines.push(line.to_string());
+    }
+    lines.join("\n")
+}
+
+pub fn add_instance_comments(
+    text: String,
+    comments: &IndexMap<String, IndexMap<String, String>>,
+) -> String {
+    let mut lines: Vec<String> = Vec::new();
+    let mut current_mod_def_name: Option<String> = None;
+    for line in text.split('\n') {
+        let trimmed_line = line.trim();
+        if trimmed_line.starts_with("endmodule") {
+            current_mod_def_name = None;
+        } else if let Some(rest) = trimmed_line.strip_prefix("module ") {
+            current_mod_def_name = rest.split(['(', ';', ' ']).next().map(|s| s.to_string());
+        } else if let Some(map_of_insts) = current_mod_def_name
+            .as_ref()
+            .and_then(|def_name| comments.get(def_name))
+        {
+            let tokens: Vec<&str> = trimmed_line.split_whitespace().collect();
+            if let [_, inst_name, rest] = tokens.as_slice() {
+                if rest.starts_with('(') {
+                    if let Some(comment) = map_of_insts.get(*inst_name) {
+                        let indent = &line[..line.len() - line.trim_start().len()];
+                        for comment_line in comment.lines() {
+                            lines.push(
+                                format!("{}// {}", indent, comment_line)
+                                    .trim_end()
+                                    .to_string(),
+                            );
+                        }
+                    }
+                }
+            }
+        }
+        lines.push(line.to_string());
+    }
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_add_module_comments() {
+        let mut comments = IndexMap::new();
+        comments.insert(
+            "ModA".to_string(),
+            "Generated by a script.\n\nDo not edit.".to_string(),
+        );
+        comments.insert("ModB".to_string(), "Leaf".to_string());
+
+        let input_verilog = "module ModA(
+  input wire a
+);
+endmodule
+module ModB #(
+  parameter N = 1
+);
+endmodule
+module ModC;
+endmodule
+"
+        .to_string();
+
+        let expected_output = "/*
+ * Generated by a script.
+ *
+ * Do not edit.
+ */
+module ModA(
+  input wire a
+);
+endmodule
+/*
+ * Leaf
+ */
+module ModB #(
+  parameter N = 1
+);
+endmodule
+module ModC;
+endmodule
+"
+        .to_string();
+
+        assert_eq!(
+            add_module_comments(input_verilog, &comments),
+            expected_output
+        );
+    }
+
+    #[test]
+    fn test_add_instance_comments() {
+        let mut comments = IndexMap::new();
+        comments.insert("ModA".to_string(), IndexMap::new());
+        comments["ModA"].insert(
+            "instB".to_string(),
+            "generator.rs:12\n\nsecond line".to_string(),
+        );
+        comments["ModA"].insert("instC".to_string(), "not emitted".to_string());
+
+        let input_verilog = "module ModA(
+  input wire a
+);
+  ModB instB (
+    .a(a)
+  );
+endmodule
+module ModC;
+  ModB instC (
+    .a(a)
+  );
+endmodule
+"
+        .to_string();
+
+        let expected_output = "module ModA(
+  input wire a
+);
+  // generator.rs:12
+  //
+  // second line
+  ModB instB (
+    .a(a)
+  );
+endmodule
+module ModC;
+  ModB instC (
+    .a(a)
+  );
+endmodule
+"
+        .to_string();
+
+        assert_eq!(
+            add_instance_comments(input_verilog, &comments),
+            expected_output
+        );
+    }
+}
@@ -0,0 +1,137 @@
+// SPDX-License-Identifier: Apache-2.0
+
+use serde::Serialize;
+
+use crate::{Assignment, ModDefCore, PipelineConfig, PortSliceOrWire, IO};
+
+#[derive(Serialize)]
+struct JsonModDef<'a> {
+    name: &'a str,
+    ports: Vec<JsonPort<'a>>,
+    instances: Vec<JsonInstance<'a>>,
+    connections: Vec<JsonConnection<'a>>,
+    inst_connections: Vec<JsonInstConnection<'a>>,
+    tieoffs: Vec<JsonTieoff>,
+    unused: Vec<String>,
+}
+
+#[derive(Serialize)]
+struct JsonPort<'a> {
+    name: &'a str,
+    direction: &'static str,
+    width: usize,
+}
+
+#[derive(Serialize)]
+struct JsonInstance<'a> {
+    name: &'a str,
+    module: String,
+}
+
+#[derive(Serialize)]
+struct JsonConnection<'a> {
+    lhs: String,
+    rhs: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pipeline: Option<JsonPipeline<'a>>,
+}
+
+#[derive(Serialize)]
+struct JsonPipeline<'a> {
+    clk: &'a str,
+    depth: usize,
+}
+
+#[derive(Serialize)]
+struct JsonInstConnection<'a> {
+    inst_port: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    port: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    net: Option<&'a str>,
+}
+
+#[derive(Serialize)]
+struct JsonTieoff {
+    port: String,
+    value: String,
+}
+
+/// Serializes the ports, instances, and connections of a module definition
+/// (without descending into its instances) as a JSON object.
+pub fn emit_json(core: &ModDefCore) -> String {
+    let ports = core
+        .ports
+        .iter()
+        .map(|(name, io)| JsonPort {
+            name,
+            direction: match io {
+                IO::Input(_) => "input",
+                IO::Output(_) => "output",
+                IO::InOut(_) => "inout",
+            },
+            width: io.width(),
+        })
+        .collect();
+
+    let instances = core
+        .instances
+        .iter()
+        .map(|(name, inst_core)| JsonInstance {
+            name,
+            module: inst_core.borrow().name.clone(),
+        })
+        .collect();
+
+    let connections = core
+        .assignments
+        .iter()
+        .map(|Assignment { lhs, rhs, pipeline }| JsonConnection {
+            lhs: lhs.local_name(),
+            rhs: rhs.local_name(),
+            pipeline: pipeline
+                .as_ref()
+                .map(|PipelineConfig { clk, depth, .. }| JsonPipeline { clk, depth: *depth }),
+        })
+        .collect();
+
+    let inst_connections = core
+        .inst_connections
+        .values()
+        .flat_map(|port_connections| port_connections.values())
+        .flatten()
+        .map(|inst_connection| {
+            let (port, net) = match &inst_connection.connected_to {
+                PortSliceOrWire::PortSlice(slice) => (Some(slice.local_name()), None),
+                PortSliceOrWire::Wire(wire) => (None, Some(wire.name.as_str())),
+            };
+            JsonInstConnection {
+                inst_port: inst_connection.inst_port_slice.local_name(),
+                port,
+                net,
+            }
+        })
+        .collect();
+
+    let tieoffs = core
+        .tieoffs
+        .iter()
+        .map(|(slice, value)| JsonTieoff {
+            port: slice.local_name(),
+            value: value.to_string(),
+        })
+        .collect();
+
+    let unused = core.unused.iter().map(|slice| slice.local_name()).collect();
+
+    serde_json::to_string_pretty(&JsonModDef {
+        name: &core.name,
+        ports,
+        instances,
+        connections,
+        inst_connections,
+        tieoffs,
+        unused,
+    })
+    .unwrap()
+}
@@ -7,21 +7,28 @@ use num_bigint::{BigInt, BigUint};
 use regex::Regex;
 use slang_rs::{self, extract_ports, str2tmpfile, SlangConfig};
 use std::cell::RefCell;
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::hash::Hash;
 use std::path::Path;
 use std::rc::{Rc, Weak};
 use xlsynth::vast::{Expr, LogicRef, VastFile, VastFileType};
 
+mod comment;
 mod enum_type;
 mod inout;
+mod json;
+mod parameter;
 mod pipeline;
+mod port_group;
+mod sv_interface;
 
+use comment::CommentInfo;
+use parameter::ParameterInfo;
 use pipeline::add_pipeline;
 use pipeline::PipelineDetails;
 
 /// Represents the direction (`Input` or `Output`) and bit width of a port.
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub enum IO {
     Input(usize),
     Output(usize),
@@ -193,6 +200,26 @@ impl PortSlice {
         self.msb - self.lsb + 1
     }
 
+    /// Returns the name of the module definition that declares the port of
+    /// this slice. For a port on a module instance, this is the name of the
+    /// instantiated module definition, not the module definition containing
+    /// the instance.
+    pub fn get_mod_def_name(&self) -> String {
+        match &self.port {
+            Port::ModDef { mod_def_core, .. } => {
+                mod_def_core.upgrade().unwrap().borrow().name.clone()
+            }
+            Port::ModInst {
+                mod_def_core,
+                inst_name,
+                ..
+            } => mod_def_core.upgrade().unwrap().borrow().instances[inst_name]
+                .borrow()
+                .name
+                .clone(),
+        }
+    }
+
     /// Create a new port called `name` on the parent module and connects it to
     /// this port slice.
     ///
@@ -240,6 +267,182 @@ impl PortSlice {
         self.export_as(&name)
     }
 
+    /// Returns `true` if this port slice and `other` refer to the same port,
+    /// regardless of their bit ranges.
+    pub fn is_same_port(&self, other: &PortSlice) -> bool {
+        match (&self.port, &other.port) {
+            (
+                Port::ModDef {
+                    mod_def_core: a_core,
+                    name: a_name,
+                },
+                Port::ModDef {
+                    mod_def_core: b_core,
+                    name: b_name,
+                },
+            ) => Weak::ptr_eq(a_core, b_core) && a_name == b_name,
+            (
+                Port::ModInst {
+                    mod_def_core: a_core,
+                    inst_name: a_inst_name,
+                    port_name: a_port_name,
+                },
+                Port::ModInst {
+                    mod_def_core: b_core,
+                    inst_name: b_inst_name,
+                    port_name: b_port_name,
+                },
+            ) => {
+                Weak::ptr_eq(a_core, b_core)
+                    && a_inst_name == b_inst_name
+                    && a_port_name == b_port_name
+            }
+            _ => false,
+        }
+    }
+
+    /// Returns `true` if this slice is part of an input port.
+    pub fn is_input(&self) -> bool {
+        matches!(self.port.io(), IO::Input(_))
+    }
+
+    /// Returns `true` if this slice is part of an output port.
+    pub fn is_output(&self) -> bool {
+        matches!(self.port.io(), IO::Output(_))
+    }
+
+    /// Returns `true` if this slice is part of an inout port.
+    pub fn is_inout(&self) -> bool {
+        matches!(self.port.io(), IO::InOut(_))
+    }
+
+    /// Merges connections involving this port slice that were made piece by
+    /// piece (e.g., bit by bit in a loop) into wider connections where
+    /// possible. Two connections are merged if they connect the same pair of
+    /// ports, neither is pipelined, and both sides of one connection are
+    /// immediately above the corresponding sides of the other. For example,
+    /// connections `a[0:0] = b[4:4]` and `a[1:1] = b[5:5]` are merged into
+    /// `a[1:0] = b[5:4]`. This does not change the connectivity of the design,
+    /// but reduces the number of `assign` statements emitted.
+    pub fn merge_connections(&self) {
+        let core_rc = self.get_mod_def_core();
+
+        // Port keys are computed by borrowing the module definition, so take
+        // the assignments out while working on them.
+        let assignments = std::mem::take(&mut core_rc.borrow_mut().assignments);
+
+        let (mut candidates, others): (Vec<_>, Vec<_>) = assignments
+            .into_iter()
+            .enumerate()
+            .partition(|(_, assignment)| {
+                assignment.pipeline.is_none()
+                    && (assignment.lhs.overlaps(self) || assignment.rhs.overlaps(self))
+            });
+
+        candidates.sort_by_key(|(_, assignment)| {
+            (
+                assignment.lhs.port.to_port_key().debug_string(),
+                assignment.rhs.port.to_port_key().debug_string(),
+                assignment.lhs.lsb,
+            )
+        });
+
+        let mut merged: Vec<(usize, Assignment)> = Vec::new();
+        for (index, assignment) in candidates {
+            if let Some((last_index, last)) = merged.last_mut() {
+                if last.lhs.is_same_port(&assignment.lhs)
+                    && last.rhs.is_same_port(&assignment.rhs)
+                    && last.lhs.msb + 1 == assignment.lhs.lsb
+                    && last.rhs.msb + 1 == assignment.rhs.lsb
+                {
+                    last.lhs.msb = assignment.lhs.msb;
+                    last.rhs.msb = assignment.rhs.msb;
+                    *last_index = (*last_index).min(index);
+                    continue;
+                }
+            }
+            merged.push((index, assignment));
+        }
+
+        let mut assignments: Vec<(usize, Assignment)> = others.into_iter().chain(merged).collect();
+        assignments.sort_by_key(|(index, _)| *index);
+        core_rc.borrow_mut().assignments = assignments
+            .into_iter()
+            .map(|(_, assignment)| assignment)
+            .collect();
+    }
+
+    /// Returns the port slice that drives this port slice within the module
+    /// definition that contains it, or `None` if it is not driven by another
+    /// port slice (e.g., if it is tied off, unconnected, or is itself a
+    /// driver such as a module definition input). Connections made via
+    /// `connect_to_net()` are followed to the instance output driving the net.
+    /// Panics if different parts of this port slice have different drivers,
+    /// or if there is more than one driver.
+    pub fn get_driver(&self) -> Option<PortSlice> {
+        let core_rc = self.get_mod_def_core();
+        let core = core_rc.borrow();
+
+        let mut drivers = Vec::new();
+
+        for Assignment { lhs, rhs, .. } in &core.assignments {
+            if lhs.overlaps(self) {
+                if self.lsb < lhs.lsb || self.msb > lhs.msb {
+                    panic!(
+                        "{} does not have a uniform driver: only {} is driven by {}",
+                        self.debug_string(),
+                        lhs.debug_string(),
+                        rhs.debug_string()
+                    );
+                }
+                drivers.push(rhs.slice_relative(self.lsb - lhs.lsb, self.width()));
+            }
+        }
+
+        let inst_connections: Vec<&InstConnection> = core
+            .inst_connections
+            .values()
+            .flat_map(|port_connections| port_connections.values())
+            .flatten()
+            .collect();
+
+        for inst_connection in inst_connections.iter() {
+            let PortSliceOrWire::Wire(wire) = &inst_connection.connected_to else {
+                continue;
+            };
+            let inst_port_slice = &inst_connection.inst_port_slice;
+            if !inst_port_slice.overlaps(self) || inst_port_slice.is_output() {
+                continue;
+            }
+            if self.lsb < inst_port_slice.lsb || self.msb > inst_port_slice.msb {
+                panic!(
+                    "{} does not have a uniform driver: only {} is connected to net {}",
+                    self.debug_string(),
+                    inst_port_slice.debug_string(),
+                    wire.name
+                );
+            }
+            let offset = self.lsb - inst_port_slice.lsb;
+            for other in inst_connections.iter() {
+                if let PortSliceOrWire::Wire(other_wire) = &other.connected_to {
+                    if other_wire.name == wire.name && other.inst_port_slice.is_output() {
+                        drivers.push(other.inst_port_slice.slice_relative(offset, self.width()));
+                    }
+                }
+            }
+        }
+
+        if drivers.len() > 1 {
+            panic!(
+                "{} has multiple drivers: {}",
+                self.debug_string(),
+                drivers.iter().map(|d| d.debug_string()).join(", ")
+            );
+        }
+
+        drivers.pop()
+    }
+
     fn slice_relative(&self, offset: usize, width: usize) -> Self {
         assert!(offset + width <= self.width());
 
@@ -303,6 +506,28 @@ pub struct PipelineConfig {
     pub depth: usize,
 }
 
+/// Formatting options for `ModDef::emit_with_options()`. None of these
+/// options change the meaning of the emitted Verilog.
+#[derive(Debug, Clone, Default)]
+pub struct EmitOptions {
+    /// If `true`, port declarations are grouped into inputs, outputs, and
+    /// inouts, with a comment before each group. Relative order within each
+    /// group is preserved.
+    pub group_ports_by_direction: bool,
+}
+
+/// Summary of how the bits of a module definition port are connected within
+/// that module definition, as returned by
+/// `ModDef::get_port_connections_summary()`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PortConnectionSummary {
+    pub port_name: String,
+    pub total_bits: usize,
+    pub connected_bits: usize,
+    pub tiedoff_bits: usize,
+    pub unused_bits: usize,
+}
+
 #[derive(Debug, Clone)]
 struct Assignment {
     pub lhs: PortSlice,
@@ -330,6 +555,62 @@ pub struct ModDefCore {
     inst_connections: IndexMap<String, IndexMap<String, Vec<InstConnection>>>,
     reserved_net_definitions: IndexMap<String, Wire>,
     enum_ports: IndexMap<String, String>,
+    parameters: IndexMap<String, (String, String)>,
+    port_width_exprs: IndexMap<String, String>,
+    port_timing_constraints: IndexMap<String, f64>,
+    comment: Option<String>,
+    inst_parameters: IndexMap<String, IndexMap<String, String>>,
+    inst_comments: IndexMap<String, String>,
+    allow_enum_mismatch: bool,
+    omit_pipeline_out_stages: bool,
+}
+
+impl ModDefCore {
+    /// Returns a description of each connection within this module definition
+    /// that does not depend on the module definition's name, for comparing
+    /// module definitions.
+    fn connection_descriptions(&self) -> Vec<String> {
+        let mut result = Vec::new();
+
+        for Assignment { lhs, rhs, pipeline } in &self.assignments {
+            match pipeline {
+                Some(pipeline) => result.push(format!(
+                    "{} = {} (pipelined, clk {}, depth {})",
+                    lhs.local_name(),
+                    rhs.local_name(),
+                    pipeline.clk,
+                    pipeline.depth
+                )),
+                None => result.push(format!("{} = {}", lhs.local_name(), rhs.local_name())),
+            }
+        }
+
+        for (slice, value) in &self.tieoffs {
+            result.push(format!("{} tied off to {}", slice.local_name(), value));
+        }
+
+        for slice in &self.unused {
+            result.push(format!("{} unused", slice.local_name()));
+        }
+
+        for port_connections in self.inst_connections.values() {
+            for inst_connections in port_connections.values() {
+                for inst_connection in inst_connections {
+                    let connected_to = match &inst_connection.connected_to {
+                        PortSliceOrWire::PortSlice(slice) => slice.local_name(),
+                        PortSliceOrWire::Wire(wire) => format!("net {}", wire.name),
+                    };
+                    result.push(format!(
+                        "{} connected to {}",
+                        inst_connection.inst_port_slice.local_name(),
+                        connected_to
+                    ));
+                }
+            }
+        }
+
+        result
+    }
 }
 
 #[derive(Clone)]
@@ -350,6 +631,25 @@ enum PortSliceOrWire {
     Wire(Wire),
 }
 
+/// A node in the walk done by `ModDef::pipeline_depths_to()`: either an output
+/// of a module definition (no instance name) or an input of one of its
+/// instances.
+type PipelineNode = (*const RefCell<ModDefCore>, Option<String>, String);
+
+/// The drivers of each node of a module definition, along with the pipeline
+/// depth of the connection from each driver.
+type PipelineDrivers = HashMap<(Option<String>, String), Vec<(Port, usize)>>;
+
+/// State shared across the walk done by `ModDef::pipeline_depths_to()`, so
+/// that each node and the drivers of each module definition are only computed
+/// once.
+#[derive(Default)]
+struct PipelineDepthCache {
+    drivers: HashMap<*const RefCell<ModDefCore>, PipelineDrivers>,
+    depths: HashMap<PipelineNode, IndexMap<String, usize>>,
+    visiting: HashSet<PipelineNode>,
+}
+
 /// Represents how a module definition should be used when validating and/or
 /// emitting Verilog.
 #[derive(PartialEq, Default, Clone)]
@@ -376,8 +676,10 @@ pub enum Usage {
     EmitDefinitionAndStop,
 }
 
+/// Identifies a port on a module definition, or a port on an instance within
+/// a module definition.
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
-enum PortKey {
+pub enum PortKey {
     ModDefPort {
         mod_def_name: String,
         port_name: String,
@@ -456,6 +758,35 @@ impl DrivenPortBits {
         self.driven == (BigUint::from(1u32) << self.width) - BigUint::from(1u32)
     }
 
+    fn drive_all(&mut self) {
+        self.driven = (BigUint::from(1u32) << self.width) - BigUint::from(1u32);
+    }
+
+    fn is_driven(&self, index: usize) -> bool {
+        self.driven.bit(index as u64)
+    }
+
+    /// Returns the contiguous ranges of undriven bits as `(msb, lsb)` tuples,
+    /// in ascending bit order.
+    fn undriven_ranges(&self) -> Vec<(usize, usize)> {
+        let mut ranges = Vec::new();
+        let mut lsb = None;
+        for i in 0..self.width {
+            match (self.is_driven(i), lsb) {
+                (false, None) => lsb = Some(i),
+                (true, Some(start)) => {
+                    ranges.push((i - 1, start));
+                    lsb = None;
+                }
+                _ => {}
+            }
+        }
+        if let Some(start) = lsb {
+            ranges.push((self.width - 1, start));
+        }
+        ranges
+    }
+
     fn example_problematic_bits(&self) -> Option<String> {
         example_problematic_bits(&self.driven, self.width)
     }
@@ -554,13 +885,23 @@ impl ModDef {
                 verilog_import: None,
                 inst_connections: IndexMap::new(),
                 reserved_net_definitions: IndexMap::new(),
+                parameters: IndexMap::new(),
+                port_width_exprs: IndexMap::new(),
+                port_timing_constraints: IndexMap::new(),
+                comment: None,
+                inst_parameters: IndexMap::new(),
+                inst_comments: IndexMap::new(),
+                allow_enum_mismatch: true,
+                omit_pipeline_out_stages: false,
             })),
         }
     }
 
     /// Returns a new module definition with the given name, using the same
-    /// ports and interfaces as the original module. The new module has no
-    /// instantiations or internal connections.
+    /// ports, interfaces, and parameters as the original module. The new
+    /// module has no instantiations or internal connections. Parameters that
+    /// were imported from Verilog along with the original module are not
+    /// copied.
     pub fn stub(&self, name: impl AsRef<str>) -> ModDef {
         let core = self.core.borrow();
         ModDef {
@@ -583,13 +924,37 @@ impl ModDef {
                 verilog_import: None,
                 inst_connections: IndexMap::new(),
                 reserved_net_definitions: IndexMap::new(),
+                parameters: if core.verilog_import.is_some() {
+                    IndexMap::new()
+                } else {
+                    core.parameters.clone()
+                },
+                port_width_exprs: core.port_width_exprs.clone(),
+                port_timing_constraints: core.port_timing_constraints.clone(),
+                comment: None,
+                inst_parameters: IndexMap::new(),
+                inst_comments: IndexMap::new(),
+                allow_enum_mismatch: true,
+                omit_pipeline_out_stages: false,
             })),
         }
     }
 
     fn frozen(&self) -> bool {
+        self.has_generated_verilog() || self.has_verilog_import()
+    }
+
+    /// Returns `true` if the Verilog for this module definition is generated
+    /// directly, rather than from its ports and instances, as is the case for
+    /// module definitions produced by `parameterize()`.
+    pub fn has_generated_verilog(&self) -> bool {
         self.core.borrow().generated_verilog.is_some()
-            || self.core.borrow().verilog_import.is_some()
+    }
+
+    /// Returns `true` if this module definition was imported from Verilog,
+    /// e.g. via `from_verilog_file()`.
+    pub fn has_verilog_import(&self) -> bool {
+        self.core.borrow().verilog_import.is_some()
     }
 
     /// Creates a new module definition from a Verilog file. The `name`
@@ -666,6 +1031,21 @@ impl ModDef {
         Self::from_verilog_using_slang(name, &cfg, skip_unsupported)
     }
 
+    /// Creates module definitions for all modules found in the given Verilog
+    /// source code, which is a string containing Verilog code. If
+    /// `skip_unsupported` is `true`, do not panic if the interface of a module
+    /// contains unsupported features; simply skip these ports.
+    pub fn from_verilog_all(verilog: impl AsRef<str>, skip_unsupported: bool) -> Vec<Self> {
+        let verilog = str2tmpfile(verilog.as_ref()).unwrap();
+
+        let cfg = SlangConfig {
+            sources: &[verilog.path().to_str().unwrap()],
+            ..Default::default()
+        };
+
+        Self::all_from_verilog_using_slang(&cfg, skip_unsupported)
+    }
+
     /// Creates a new module definition from Verilog sources. The `name`
     /// parameter is the name of the module to extract from Verilog code, and
     /// `cfg` is a `SlangConfig` struct specifying source files, include
@@ -686,22 +1066,101 @@ impl ModDef {
             )
         });
 
-        Self::mod_def_from_parser_ports(name.as_ref(), selected, cfg, skip_unsupported)
+        let parameters: Vec<parameter::ParsedParameter> = Self::read_sources(cfg)
+            .flat_map(|verilog| parameter::parse_parameters(&verilog, name.as_ref()))
+            .collect();
+        Self::mod_def_from_parser_ports(name.as_ref(), selected, &parameters, cfg, skip_unsupported)
     }
 
+    /// Creates module definitions for all modules found in the given Verilog
+    /// sources, sorted by module name so that the result does not depend on
+    /// the iteration order of the parser output.
     pub fn all_from_verilog_using_slang(cfg: &SlangConfig, skip_unsupported: bool) -> Vec<Self> {
         let parser_ports = extract_ports(cfg, skip_unsupported);
+        let parameters = Self::parse_source_parameters(cfg);
         parser_ports
             .keys()
+            .sorted()
             .map(|name| {
-                Self::mod_def_from_parser_ports(name, &parser_ports[name], cfg, skip_unsupported)
+                Self::mod_def_from_parser_ports(
+                    name,
+                    &parser_ports[name],
+                    parameters.get(name).map_or(&[], Vec::as_slice),
+                    cfg,
+                    skip_unsupported,
+                )
             })
             .collect()
     }
 
+    /// Parses the `parameter` declarations of all modules in the Verilog
+    /// sources of `cfg`, reading and scanning each source file only once.
+    fn parse_source_parameters(
+        cfg: &SlangConfig,
+    ) -> IndexMap<String, Vec<parameter::ParsedParameter>> {
+        let mut result: IndexMap<String, Vec<parameter::ParsedParameter>> = IndexMap::new();
+        for verilog in Self::read_sources(cfg) {
+            for (name, parameters) in parameter::parse_all_parameters(&verilog) {
+                result.entry(name).or_default().extend(parameters);
+            }
+        }
+        result
+    }
+
+    /// Returns the contents of each Verilog source of `cfg` that can be read.
+    fn read_sources<'a>(cfg: &'a SlangConfig) -> impl Iterator<Item = String> + 'a {
+        cfg.sources
+            .iter()
+            .filter_map(|source| std::fs::read_to_string(source).ok())
+    }
+
+    /// Creates a stub module definition from the first SystemVerilog
+    /// `interface ... endinterface` block in `sv_source`. The module
+    /// definition has the same name as the interface and a port for each
+    /// `logic` signal listed in the first modport of the interface, with the
+    /// direction given by that modport. Returns the module definition along
+    /// with an interface defined on it with the same name, whose functions are
+    /// the signal names. The module definition is returned as well because an
+    /// interface only holds a weak reference to its module definition, which
+    /// would otherwise be dropped.
+    ///
+    /// Only basic interfaces are supported: signals must be declared with
+    /// `logic` and at most one packed dimension whose bounds are integer
+    /// literals, and modports may only list plain signals.
+    pub fn from_systemverilog_interface(sv_source: impl AsRef<str>) -> (ModDef, Intf) {
+        let parsed = sv_interface::parse_interface(sv_source.as_ref())
+            .unwrap_or_else(|| panic!("No interface definition found in SystemVerilog source"));
+
+        let Some((modport_name, directions)) = parsed.modports.first() else {
+            panic!("Interface {} does not declare any modports", parsed.name);
+        };
+
+        let mod_def = ModDef::new(&parsed.name);
+        let mut mapping = IndexMap::new();
+        for (signal, direction) in directions {
+            let width = *parsed.signals.get(signal).unwrap_or_else(|| {
+                panic!(
+                    "Modport {}.{} lists {}, which is not a logic signal of the interface",
+                    parsed.name, modport_name, signal
+                )
+            });
+            let io = match direction.as_str() {
+                "input" => IO::Input(width),
+                "output" => IO::Output(width),
+                _ => IO::InOut(width),
+            };
+            mod_def.add_port(signal, io);
+            mapping.insert(signal.clone(), (signal.clone(), width - 1, 0));
+        }
+        let intf = mod_def.def_intf(&parsed.name, mapping);
+
+        (mod_def, intf)
+    }
+
     fn mod_def_from_parser_ports(
         mod_def_name: &str,
         parser_ports: &[slang_rs::Port],
+        parameters: &[parameter::ParsedParameter],
         cfg: &SlangConfig,
         skip_unsupported: bool,
     ) -> ModDef {
@@ -711,8 +1170,9 @@ impl ModDef {
             match parser_port_to_port(parser_port) {
                 Ok((name, io)) => {
                     ports.insert(name.clone(), io.clone());
-                    // Enum input ports that are not a packed array require special handling
-                    // They need to have casting to be valid Verilog.
+                    // Enum ports that are not a packed array require special handling: inputs
+                    // need to have casting to be valid Verilog, and connections are checked for
+                    // consistent enum types during validation.
                     if let slang_rs::Type::Enum {
                         name: enum_name,
                         packed_dimensions,
@@ -721,9 +1181,7 @@ impl ModDef {
                     } = &parser_port.ty
                     {
                         if packed_dimensions.is_empty() && unpacked_dimensions.is_empty() {
-                            if let IO::Input(_) = io {
-                                enum_ports.insert(name.clone(), enum_name.clone());
-                            }
+                            enum_ports.insert(name.clone(), enum_name.clone());
                         }
                     }
                 }
@@ -737,7 +1195,7 @@ impl ModDef {
             }
         }
 
-        ModDef {
+        let mod_def = ModDef {
             core: Rc::new(RefCell::new(ModDefCore {
                 name: mod_def_name.to_string(),
                 ports,
@@ -763,10 +1221,57 @@ impl ModDef {
                 }),
                 inst_connections: IndexMap::new(),
                 reserved_net_definitions: IndexMap::new(),
+                parameters: IndexMap::new(),
+                port_width_exprs: IndexMap::new(),
+                port_timing_constraints: IndexMap::new(),
+                comment: None,
+                inst_parameters: IndexMap::new(),
+                inst_comments: IndexMap::new(),
+                allow_enum_mismatch: true,
+                omit_pipeline_out_stages: false,
             })),
+        };
+
+        {
+            let mut core = mod_def.core.borrow_mut();
+            for parameter::ParsedParameter { name, ty, default } in parameters {
+                core.parameters
+                    .insert(name.clone(), (ty.clone(), default.clone()));
+            }
+        }
+
+        mod_def
+    }
+
+    /// Parses the `parameter` declarations of the module with the same name as
+    /// this module definition in the given Verilog code, and records them as
+    /// parameters of this module definition, replacing any existing parameters
+    /// with the same names. This is done automatically for module definitions
+    /// created from Verilog sources, although their parameters are not
+    /// emitted when such module definitions are stubbed (e.g. with
+    /// `Usage::EmitStubAndStop`). Declarations are parsed without
+    /// preprocessing, and `localparam` declarations, type parameters, and
+    /// parameters without a default value are skipped.
+    pub fn import_parameters_from_verilog(&self, verilog: impl AsRef<str>) {
+        let mut core = self.core.borrow_mut();
+        for parameter::ParsedParameter { name, ty, default } in
+            parameter::parse_parameters(verilog.as_ref(), &core.name)
+        {
+            core.parameters.insert(name, (ty, default));
         }
     }
 
+    /// Returns the default value of the parameter with the given name, or
+    /// `None` if the parameter does not exist or its default value is not an
+    /// integer literal, such as `8` or `8'h08`.
+    pub fn get_default_parameter_value(&self, name: impl AsRef<str>) -> Option<BigUint> {
+        self.core
+            .borrow()
+            .parameters
+            .get(name.as_ref())
+            .and_then(|(_, default)| parameter::parse_integer_literal(default))
+    }
+
     /// Adds a port to the module definition with the given name. The direction
     /// and width are specfied via the `io` parameter.
     pub fn add_port(&self, name: impl AsRef<str>, io: IO) -> Port {
@@ -792,6 +1297,132 @@ impl ModDef {
         }
     }
 
+    /// Declares a parameter on this module definition with the given name,
+    /// type, and default value, which will be emitted in a `#(parameter ...)`
+    /// header on the module declaration. `ty` is the Verilog type of the
+    /// parameter (e.g., `"int"`), or an empty string for an untyped parameter.
+    /// `default` is a Verilog expression. Instance parameter overrides within
+    /// this module definition can refer to the parameter by name (see
+    /// `ModInst::set_parameter()`). Panics if a parameter with the same name
+    /// has already been declared.
+    pub fn add_parameter(
+        &self,
+        name: impl AsRef<str>,
+        ty: impl AsRef<str>,
+        default: impl AsRef<str>,
+    ) {
+        if self.frozen() {
+            panic!(
+                "Module {} is frozen. wrap() first if modifications are needed.",
+                self.core.borrow().name
+            );
+        }
+
+        let mut core = self.core.borrow_mut();
+        match core.parameters.entry(name.as_ref().to_string()) {
+            Entry::Occupied(_) => {
+                panic!("Parameter {}.{} already exists.", core.name, name.as_ref())
+            }
+            Entry::Vacant(entry) => {
+                entry.insert((ty.as_ref().to_string(), default.as_ref().to_string()));
+            }
+        }
+    }
+
+    /// Specifies that the width of the port with the given name should be
+    /// emitted as the Verilog expression `expr`, which will typically refer to
+    /// parameters declared with `add_parameter()`. For example, if `expr` is
+    /// `"WIDTH"`, the port is declared as `[WIDTH-1:0]`, full-width references
+    /// to the port within this module definition are emitted in the same way,
+    /// and full-width tieoffs are cast to the width `(WIDTH)`. The numeric
+    /// width of the port is still used for validation and for connections
+    /// within topstitch, so it should match the value of `expr` when
+    /// parameters are at their default values. Panics if the port does not
+    /// exist.
+    pub fn set_port_width_expr(&self, name: impl AsRef<str>, expr: impl AsRef<str>) {
+        if self.frozen() {
+            panic!(
+                "Module {} is frozen. wrap() first if modifications are needed.",
+                self.core.borrow().name
+            );
+        }
+
+        if !self.has_port(name.as_ref()) {
+            panic!(
+                "Port {}.{} does not exist",
+                self.core.borrow().name,
+                name.as_ref()
+            );
+        }
+
+        self.core
+            .borrow_mut()
+            .port_width_exprs
+            .insert(name.as_ref().to_string(), expr.as_ref().to_string());
+    }
+
+    /// Changes the direction of the port with the given name from input to
+    /// output, or vice versa, keeping its width; inout ports are left
+    /// unchanged. This may be used on module definitions imported from
+    /// Verilog, e.g. when adapting a module for use in a different context.
+    /// Panics if the port does not exist or if it has already been connected,
+    /// tied off, or marked as unused within this module definition.
+    pub fn swap_port_direction(&self, name: impl AsRef<str>) {
+        let port_slice = self.get_port(name.as_ref()).to_port_slice();
+        if port_slice.has_connections(&self.core.borrow()) {
+            panic!(
+                "Cannot swap the direction of port {}: it is already connected",
+                port_slice.port.debug_string()
+            );
+        }
+
+        let mut core = self.core.borrow_mut();
+        let io = core.ports.get_mut(name.as_ref()).unwrap();
+        *io = match io {
+            IO::Input(width) => IO::Output(*width),
+            IO::Output(width) => IO::Input(*width),
+            IO::InOut(width) => IO::InOut(*width),
+        };
+    }
+
+    /// Sets a comment to be emitted as a `/* ... */` block immediately before
+    /// the declaration of this module definition, e.g. to record generation
+    /// metadata. The comment may span multiple lines. Panics if the comment
+    /// contains `*/`.
+    pub fn set_module_comment(&self, comment: impl AsRef<str>) {
+        if comment.as_ref().contains("*/") {
+            panic!(
+                "Comment for module {} cannot contain \"*/\"",
+                self.core.borrow().name
+            );
+        }
+        self.core.borrow_mut().comment = Some(comment.as_ref().to_string());
+    }
+
+    /// If `allow` is `false`, validation of this module definition fails for
+    /// connections between enum-typed ports and ports that are not enum-typed,
+    /// since they may hide type errors in the generated Verilog. By default,
+    /// such connections are allowed, and connections to enum-typed inputs are
+    /// cast to the enum type when emitted.
+    pub fn set_allow_enum_mismatch(&self, allow: bool) {
+        self.core.borrow_mut().allow_enum_mismatch = allow;
+    }
+
+    /// If `omit` is `true`, pipeline instances emitted for pipelined
+    /// connections in this module definition leave out the `out_stages` port,
+    /// rather than leaving it unconnected. This is needed when the pipeline
+    /// primitive does not have an `out_stages` port. By default, the port is
+    /// included.
+    pub fn set_omit_pipeline_out_stages(&self, omit: bool) {
+        self.core.borrow_mut().omit_pipeline_out_stages = omit;
+    }
+
+    /// Returns `true` if this module definition has a parameter with the given
+    /// name.
+    pub fn has_parameter(&self, name: impl AsRef<str>) -> bool {
+        self.core.borrow().parameters.contains_key(name.as_ref())
+    }
+
     /// Returns `true` if this module definition has a port with the given name.
     pub fn has_port(&self, name: impl AsRef<str>) -> bool {
         self.core.borrow().ports.contains_key(name.as_ref())
@@ -817,6 +1448,68 @@ impl ModDef {
         }
     }
 
+    /// Returns the port on this module definition whose name matches `name`,
+    /// ignoring case. This is useful when port names have been normalized to
+    /// a different case by an import flow. Panics if no port matches, or if
+    /// more than one port matches (i.e., port names differ only by case).
+    pub fn get_port_ci(&self, name: impl AsRef<str>) -> Port {
+        let matches: Vec<String> = self
+            .core
+            .borrow()
+            .ports
+            .keys()
+            .filter(|port_name| port_name.eq_ignore_ascii_case(name.as_ref()))
+            .cloned()
+            .collect();
+
+        match matches.as_slice() {
+            [port_name] => self.get_port(port_name),
+            [] => panic!(
+                "Port {}.{} does not exist (case-insensitive match)",
+                self.get_name(),
+                name.as_ref()
+            ),
+            _ => panic!(
+                "Case-insensitive match for port {}.{} is ambiguous: {}",
+                self.get_name(),
+                name.as_ref(),
+                matches.join(", ")
+            ),
+        }
+    }
+
+    /// Checks that each named port on this module definition exists and has
+    /// the expected width. All mismatches are collected and reported in a
+    /// single panic message, rather than failing on the first one.
+    pub fn assert_port_widths(&self, expected: &[(&str, usize)]) {
+        let core = self.core.borrow();
+        let mut errors = Vec::new();
+
+        for (name, width) in expected {
+            match core.ports.get(*name) {
+                Some(io) => {
+                    if io.width() != *width {
+                        errors.push(format!(
+                            "{}.{}: expected width {}, actual width {}",
+                            core.name,
+                            name,
+                            width,
+                            io.width()
+                        ));
+                    }
+                }
+                None => errors.push(format!(
+                    "{}.{}: expected width {}, but port does not exist",
+                    core.name, name, width
+                )),
+            }
+        }
+
+        if !errors.is_empty() {
+            panic!("Port width mismatches:\n{}", errors.join("\n"));
+        }
+    }
+
     /// Returns a slice of the port on this module definition with the given
     /// name, from `msb` down to `lsb`, inclusive; panics if a port with that
     /// name does not exist.
@@ -840,30 +1533,294 @@ impl ModDef {
         result
     }
 
-    /// Walk through all instances within this module definition, marking those
-    /// whose names match the given regex with the usage
-    /// `Usage::EmitStubAndStop`. Repeat recursively for all instances whose
-    /// names do not match this regex.
-    pub fn stub_recursive(&self, regex: impl AsRef<str>) {
-        let regex_compiled = Regex::new(regex.as_ref()).unwrap();
-        let mut visited = HashSet::new();
-        self.stub_recursive_helper(&regex_compiled, &mut visited);
+    /// Returns the port at position `index` in the order in which ports were
+    /// declared on this module definition, or `None` if `index` is out of
+    /// range.
+    pub fn get_port_by_index(&self, index: usize) -> Option<Port> {
+        let inner = self.core.borrow();
+        inner.ports.get_index(index).map(|(name, _)| Port::ModDef {
+            name: name.clone(),
+            mod_def_core: Rc::downgrade(&self.core),
+        })
     }
 
-    fn stub_recursive_helper(&self, regex: &Regex, visited: &mut HashSet<String>) {
-        for inst in self.get_instances() {
-            let mod_def = inst.get_mod_def();
-            let mod_def_name = mod_def.get_name();
-            if regex.is_match(mod_def_name.as_str()) {
-                mod_def.set_usage(Usage::EmitStubAndStop);
-            } else if !visited.contains(&mod_def_name) {
-                visited.insert(mod_def_name);
-                mod_def.stub_recursive_helper(regex, visited);
-            }
-        }
+    /// Returns the number of ports on this module definition.
+    pub fn get_port_count(&self) -> usize {
+        self.core.borrow().ports.len()
     }
 
-    /// Returns the name of this module definition.
+    /// Reorders the ports of this module definition to match the order of the
+    /// ports with the same names on `reference`, which determines the order
+    /// of the port list in the emitted Verilog. This is useful when wrapping
+    /// a module definition, since the ports of the wrapper may have been
+    /// added in a different order. Ports that `reference` does not have are
+    /// placed after the others, in their existing relative order.
+    pub fn canonicalize_port_order_to_match(&self, reference: &ModDef) {
+        if self.frozen() {
+            panic!(
+                "Module {} is frozen. wrap() first if modifications are needed.",
+                self.get_name()
+            );
+        }
+
+        let reference_order: HashMap<String, usize> = reference
+            .core
+            .borrow()
+            .ports
+            .keys()
+            .enumerate()
+            .map(|(index, name)| (name.clone(), index))
+            .collect();
+
+        self.core
+            .borrow_mut()
+            .ports
+            .sort_by_cached_key(|name, _| reference_order.get(name).copied().unwrap_or(usize::MAX));
+    }
+
+    /// Returns the port slice corresponding to the net with the given name in
+    /// the emitted Verilog for this module definition, or `None` if no such
+    /// net exists. Module definition ports are matched by name, nets created
+    /// with `connect_to_net()` (or for instance-to-instance inout connections)
+    /// are matched by their wire name, and all other instance port nets are
+    /// matched by their generated name, `{instance name}_{port name}`.
+    pub fn get_named_net(&self, net_name: impl AsRef<str>) -> Option<PortSlice> {
+        let net_name = net_name.as_ref();
+
+        if self.has_port(net_name) {
+            return Some(self.get_port(net_name).to_port_slice());
+        }
+
+        let core = self.core.borrow();
+
+        for port_connections in core.inst_connections.values() {
+            for inst_connections in port_connections.values() {
+                for inst_connection in inst_connections {
+                    if let PortSliceOrWire::Wire(wire) = &inst_connection.connected_to {
+                        if wire.name == net_name {
+                            return Some(inst_connection.inst_port_slice.clone());
+                        }
+                    }
+                }
+            }
+        }
+
+        for (inst_name, inst) in core.instances.iter() {
+            for port_name in inst.borrow().ports.keys() {
+                if format!("{}_{}", inst_name, port_name) == net_name {
+                    return Some(
+                        Port::ModInst {
+                            inst_name: inst_name.clone(),
+                            port_name: port_name.clone(),
+                            mod_def_core: Rc::downgrade(&self.core),
+                        }
+                        .to_port_slice(),
+                    );
+                }
+            }
+        }
+
+        None
+    }
+
+    /// Walk through all instances within this module definition, marking those
+    /// whose names match the given regex with the usage
+    /// `Usage::EmitStubAndStop`. Repeat recursively for all instances whose
+    /// names do not match this regex.
+    pub fn stub_recursive(&self, regex: impl AsRef<str>) {
+        let regex_compiled = Regex::new(regex.as_ref()).unwrap();
+        let mut visited = HashSet::new();
+        self.stub_recursive_helper(&regex_compiled, &mut visited);
+    }
+
+    fn stub_recursive_helper(&self, regex: &Regex, visited: &mut HashSet<String>) {
+        for inst in self.get_instances() {
+            let mod_def = inst.get_mod_def();
+            let mod_def_name = mod_def.get_name();
+            if regex.is_match(mod_def_name.as_str()) {
+                mod_def.set_usage(Usage::EmitStubAndStop);
+            } else if !visited.contains(&mod_def_name) {
+                visited.insert(mod_def_name);
+                mod_def.stub_recursive_helper(regex, visited);
+            }
+        }
+    }
+
+    /// Returns a human-readable list of the structural differences between this
+    /// module definition and `other`, covering ports (added, removed, or with a
+    /// different direction or width), instances (added, removed, or of a
+    /// different module definition), and connections (assignments, tieoffs,
+    /// unused markings, and instance connections). "Added" refers to something
+    /// present in `other` but not in this module definition, and "removed" to
+    /// the opposite. Returns an empty vector if no differences are found.
+    pub fn diff(&self, other: &ModDef) -> Vec<String> {
+        let mut result = Vec::new();
+
+        let self_core = self.core.borrow();
+        let other_core = other.core.borrow();
+
+        for (name, io) in self_core.ports.iter() {
+            match other_core.ports.get(name) {
+                None => result.push(format!("Port {} removed", name)),
+                Some(other_io) if io != other_io => result.push(format!(
+                    "Port {} changed from {:?} to {:?}",
+                    name, io, other_io
+                )),
+                Some(_) => {}
+            }
+        }
+        for (name, io) in other_core.ports.iter() {
+            if !self_core.ports.contains_key(name) {
+                result.push(format!("Port {} added: {:?}", name, io));
+            }
+        }
+
+        for (name, inst) in self_core.instances.iter() {
+            match other_core.instances.get(name) {
+                None => result.push(format!("Instance {} removed", name)),
+                Some(other_inst) if inst.borrow().name != other_inst.borrow().name => {
+                    result.push(format!(
+                        "Instance {} changed from {} to {}",
+                        name,
+                        inst.borrow().name,
+                        other_inst.borrow().name
+                    ))
+                }
+                Some(_) => {}
+            }
+        }
+        for (name, inst) in other_core.instances.iter() {
+            if !self_core.instances.contains_key(name) {
+                result.push(format!("Instance {} added: {}", name, inst.borrow().name));
+            }
+        }
+
+        // Connections are compared as multisets, so that a connection made
+        // more times on one side than on the other is also reported.
+        let self_connections = self_core.connection_descriptions();
+        let other_connections = other_core.connection_descriptions();
+        fn count(connections: &[String]) -> HashMap<&str, usize> {
+            let mut counts: HashMap<&str, usize> = HashMap::new();
+            for connection in connections {
+                *counts.entry(connection.as_str()).or_default() += 1;
+            }
+            counts
+        }
+        let mut unmatched_other = count(&other_connections);
+        for connection in self_connections.iter() {
+            match unmatched_other.get_mut(connection.as_str()) {
+                Some(n) if *n > 0 => *n -= 1,
+                _ => result.push(format!("Connection removed: {}", connection)),
+            }
+        }
+        let mut unmatched_self = count(&self_connections);
+        for connection in other_connections.iter() {
+            match unmatched_self.get_mut(connection.as_str()) {
+                Some(n) if *n > 0 => *n -= 1,
+                _ => result.push(format!("Connection added: {}", connection)),
+            }
+        }
+
+        result
+    }
+
+    /// Returns the names of the interfaces on this module definition for which
+    /// no port slice in the interface has been connected, tied off, or marked
+    /// as unused within this module definition.
+    pub fn get_unused_interfaces(&self) -> Vec<String> {
+        let intf_names: Vec<String> = self.core.borrow().interfaces.keys().cloned().collect();
+        let core = self.core.borrow();
+        intf_names
+            .into_iter()
+            .filter(|intf_name| {
+                !self
+                    .get_intf(intf_name)
+                    .get_port_slices()
+                    .values()
+                    .any(|port_slice| port_slice.has_connections(&core))
+            })
+            .collect()
+    }
+
+    /// Returns a Graphviz DOT directed graph of the module hierarchy, where
+    /// nodes are module definitions and edges are instantiations, labeled with
+    /// the instance name. If `recursive` is `false`, only the instances
+    /// directly within this module definition are included; otherwise, the
+    /// graph descends into each instantiated module definition.
+    pub fn emit_dot_graph(&self, recursive: bool) -> String {
+        let mut lines = vec![format!("digraph \"{}\" {{", self.get_name())];
+        let mut visited = HashSet::new();
+        self.emit_dot_graph_helper(recursive, &mut visited, &mut lines);
+        lines.push("}".to_string());
+        lines
+            .into_iter()
+            .map(|line| format!("{}\n", line))
+            .collect()
+    }
+
+    fn emit_dot_graph_helper(
+        &self,
+        recursive: bool,
+        visited: &mut HashSet<String>,
+        lines: &mut Vec<String>,
+    ) {
+        let name = self.get_name();
+        visited.insert(name.clone());
+        lines.push(format!("  \"{}\";", name));
+
+        let instances = self.get_instances();
+        for inst in instances.iter() {
+            lines.push(format!(
+                "  \"{}\" -> \"{}\" [label=\"{}\"];",
+                name,
+                inst.get_mod_def().get_name(),
+                inst.name
+            ));
+        }
+
+        if recursive {
+            for inst in instances.iter() {
+                let mod_def = inst.get_mod_def();
+                if !visited.contains(&mod_def.get_name()) {
+                    mod_def.emit_dot_graph_helper(recursive, visited, lines);
+                }
+            }
+        }
+    }
+
+    /// Returns the maximum depth of the instance hierarchy beneath this module
+    /// definition: 0 if it has no instances, otherwise 1 plus the maximum
+    /// depth of the module definitions it instantiates. Like `validate()`, this
+    /// does not descend into module definitions whose usage is not
+    /// `EmitDefinitionAndDescend`; they are counted as having no instances.
+    pub fn get_hierarchy_depth(&self) -> usize {
+        let mut depths = HashMap::new();
+        self.get_hierarchy_depth_helper(&mut depths)
+    }
+
+    fn get_hierarchy_depth_helper(&self, depths: &mut HashMap<String, usize>) -> usize {
+        if self.core.borrow().usage != Usage::EmitDefinitionAndDescend {
+            return 0;
+        }
+
+        let mut result = 0;
+        for inst in self.get_instances() {
+            let mod_def = inst.get_mod_def();
+            let mod_def_name = mod_def.get_name();
+            let depth = match depths.get(&mod_def_name) {
+                Some(depth) => *depth,
+                None => {
+                    let depth = mod_def.get_hierarchy_depth_helper(depths);
+                    depths.insert(mod_def_name, depth);
+                    depth
+                }
+            };
+            result = result.max(1 + depth);
+        }
+        result
+    }
+
+    /// Returns the name of this module definition.
     pub fn get_name(&self) -> String {
         self.core.borrow().name.clone()
     }
@@ -942,6 +1899,14 @@ impl ModDef {
             );
         }
 
+        if moddef.contains_mod_def_core(&self.core, &mut HashSet::new()) {
+            panic!(
+                "Recursive instantiation of {} (via {})",
+                self.core.borrow().name,
+                moddef.core.borrow().name
+            );
+        }
+
         {
             let mut inner = self.core.borrow_mut();
             if inner.instances.contains_key(name) {
@@ -981,6 +1946,48 @@ impl ModDef {
         inst
     }
 
+    /// Returns `true` if `core` is this module definition or a module
+    /// definition instantiated anywhere in its hierarchy. `visited` holds the
+    /// module definitions that have already been checked, so that module
+    /// definitions instantiated in several places are only checked once.
+    fn contains_mod_def_core(
+        &self,
+        core: &Rc<RefCell<ModDefCore>>,
+        visited: &mut HashSet<*const RefCell<ModDefCore>>,
+    ) -> bool {
+        if Rc::ptr_eq(&self.core, core) {
+            return true;
+        }
+        if !visited.insert(Rc::as_ptr(&self.core)) {
+            return false;
+        }
+        let inst_cores: Vec<Rc<RefCell<ModDefCore>>> =
+            self.core.borrow().instances.values().cloned().collect();
+        inst_cores
+            .into_iter()
+            .any(|inst_core| ModDef { core: inst_core }.contains_mod_def_core(core, visited))
+    }
+
+    /// Instantiates `moddef` within this module definition and exports every
+    /// port of the new instance to a port on this module definition with the
+    /// same name. `name` has the same meaning as in `instantiate()`. Panics if
+    /// any of the exported port names already exist on this module definition;
+    /// this is checked before instantiating, so that nothing is added to this
+    /// module definition in that case.
+    pub fn instantiate_and_export_all(&self, moddef: &ModDef, name: Option<&str>) -> ModInst {
+        for port_name in moddef.core.borrow().ports.keys() {
+            if self.has_port(port_name) {
+                panic!("Port {}.{} already exists.", self.get_name(), port_name);
+            }
+        }
+
+        let inst = self.instantiate(moddef, name, None);
+        for port in inst.get_ports(None) {
+            port.export();
+        }
+        inst
+    }
+
     /// Create one or more instances of a module, using the provided dimensions.
     /// For example, if `dimensions` is `&[3]`, TopStitch will create a 1D array
     /// of 3 instances, called `<mod_def_name>_i_0`, `<mod_def_name>_i_1`,
@@ -1067,10 +2074,154 @@ impl ModDef {
         std::fs::write(path, self.emit(validate)).expect(&err_msg);
     }
 
+    /// Writes Verilog code for this module definition to the given writer. If
+    /// `validate` is `true`, validate the module definition before emitting
+    /// Verilog.
+    pub fn emit_to_writer(
+        &self,
+        w: &mut impl std::io::Write,
+        validate: bool,
+    ) -> std::io::Result<()> {
+        w.write_all(self.emit(validate).as_bytes())
+    }
+
+    /// Sets the timing constraint for the port on this module definition with
+    /// the given name, for use by `emit_sdc()`. For clock ports, `value` is the
+    /// clock period; for all other ports, it is the input or output delay.
+    /// Panics if the port does not exist.
+    pub fn set_port_timing_constraint(&self, name: impl AsRef<str>, value: f64) {
+        if !self.has_port(name.as_ref()) {
+            panic!(
+                "Port {}.{} does not exist",
+                self.core.borrow().name,
+                name.as_ref()
+            );
+        }
+
+        self.core
+            .borrow_mut()
+            .port_timing_constraints
+            .insert(name.as_ref().to_string(), value);
+    }
+
+    /// Returns Synopsys Design Constraints (SDC) for the ports of this module
+    /// definition as a string. Clock ports are input ports that are used as
+    /// the clock of a pipelined connection within this module definition, or
+    /// whose names are `clk`/`clock` or end in `_clk`/`_clock`. A
+    /// `create_clock` command is emitted for each clock port whose period has
+    /// been set with `set_port_timing_constraint()`; clock ports without a
+    /// period are left out, since a period of 0 is not valid. For all other
+    /// ports, `set_input_delay` and/or `set_output_delay` commands are emitted
+    /// relative to the first clock that was emitted. Delays default to 0, and
+    /// can be overridden with `set_port_timing_constraint()`.
+    pub fn emit_sdc(&self) -> String {
+        let core = self.core.borrow();
+
+        let pipeline_clocks: HashSet<&str> = core
+            .assignments
+            .iter()
+            .filter_map(|assignment| assignment.pipeline.as_ref())
+            .map(|pipeline| pipeline.clk.as_str())
+            .collect();
+
+        let is_clock = |name: &str, io: &IO| {
+            matches!(io, IO::Input(1))
+                && (pipeline_clocks.contains(name)
+                    || name == "clk"
+                    || name == "clock"
+                    || name.ends_with("_clk")
+                    || name.ends_with("_clock"))
+        };
+
+        let constraint = |name: &str| {
+            core.port_timing_constraints
+                .get(name)
+                .copied()
+                .unwrap_or(0.0)
+        };
+
+        let mut lines = Vec::new();
+        let mut clocks = Vec::new();
+
+        for (name, io) in core.ports.iter() {
+            if is_clock(name, io) {
+                if let Some(period) = core.port_timing_constraints.get(name) {
+                    lines.push(format!(
+                        "create_clock -name {} -period {} [get_ports {}]",
+                        name, period, name
+                    ));
+                    clocks.push(name.as_str());
+                }
+            }
+        }
+
+        let clock_option = match clocks.first() {
+            Some(clock) => format!(" -clock {}", clock),
+            None => String::new(),
+        };
+
+        for (name, io) in core.ports.iter() {
+            if is_clock(name, io) {
+                continue;
+            }
+            if let IO::Input(_) | IO::InOut(_) = io {
+                lines.push(format!(
+                    "set_input_delay {}{} [get_ports {}]",
+                    constraint(name),
+                    clock_option,
+                    name
+                ));
+            }
+            if let IO::Output(_) | IO::InOut(_) = io {
+                lines.push(format!(
+                    "set_output_delay {}{} [get_ports {}]",
+                    constraint(name),
+                    clock_option,
+                    name
+                ));
+            }
+        }
+
+        lines
+            .into_iter()
+            .map(|line| format!("{}\n", line))
+            .collect()
+    }
+
+    /// Returns the Verilog declaration of this module definition, consisting
+    /// of the `module` line with its parameters and port list, without the
+    /// module body or `endmodule`. Instances are not emitted.
+    pub fn emit_port_list_header(&self) -> String {
+        let text = self.stub(self.get_name()).emit(false);
+        let header = text
+            .trim_end()
+            .strip_suffix("endmodule")
+            .unwrap_or_else(|| {
+                panic!(
+                    "Unexpected Verilog emitted for module {}: missing endmodule",
+                    self.get_name()
+                )
+            });
+        format!("{}\n", header.trim_end())
+    }
+
+    /// Returns a JSON object describing this module definition: its name, its
+    /// ports (name, direction, and width), its instances (instance name and
+    /// module definition name), and the connections, tieoffs, and unused
+    /// markings within it. Instances are not described recursively.
+    pub fn emit_json(&self) -> String {
+        json::emit_json(&self.core.borrow())
+    }
+
     /// Returns Verilog code for this module definition as a string. If
     /// `validate` is `true`, validate the module definition before emitting
     /// Verilog.
     pub fn emit(&self, validate: bool) -> String {
+        self.emit_with_options(validate, &EmitOptions::default())
+    }
+
+    /// Same as `emit()`, with additional formatting options.
+    pub fn emit_with_options(&self, validate: bool, options: &EmitOptions) -> String {
         if validate {
             self.validate();
         }
@@ -1078,19 +2229,29 @@ impl ModDef {
         let mut file = VastFile::new(VastFileType::SystemVerilog);
         let mut leaf_text = Vec::new();
         let mut enum_remapping = IndexMap::new();
+        let mut parameter_info = ParameterInfo::default();
+        let mut comment_info = CommentInfo::default();
         self.emit_recursive(
             &mut emitted_module_names,
             &mut file,
             &mut leaf_text,
             &mut enum_remapping,
+            &mut parameter_info,
+            &mut comment_info,
         );
-        let emit_result = file.emit();
+        let mut emit_result = file.emit();
+        if options.group_ports_by_direction {
+            emit_result = port_group::group_ports_by_direction(emit_result);
+        }
         if !emit_result.is_empty() {
             leaf_text.push(emit_result);
         }
         let result = leaf_text.join("\n");
         let result = inout::rename_inout(result);
-        enum_type::remap_enum_types(result, &enum_remapping)
+        let result = enum_type::remap_enum_types(result, &enum_remapping);
+        let result = comment::add_instance_comments(result, &comment_info.instances);
+        let result = parameter::add_parameters(result, &parameter_info);
+        comment::add_module_comments(result, &comment_info.modules)
     }
 
     fn emit_recursive(
@@ -1099,6 +2260,8 @@ impl ModDef {
         file: &mut VastFile,
         leaf_text: &mut Vec<String>,
         enum_remapping: &mut IndexMap<String, IndexMap<String, IndexMap<String, String>>>,
+        parameter_info: &mut ParameterInfo,
+        comment_info: &mut CommentInfo,
     ) {
         let core = self.core.borrow();
         let mut pipeline_counter = 0usize..;
@@ -1119,7 +2282,15 @@ impl ModDef {
 
         if core.usage == Usage::EmitNothingAndStop {
             return;
-        } else if core.usage == Usage::EmitDefinitionAndStop {
+        }
+
+        if let Some(comment) = &core.comment {
+            comment_info
+                .modules
+                .insert(core.name.clone(), comment.clone());
+        }
+
+        if core.usage == Usage::EmitDefinitionAndStop {
             leaf_text.push(core.generated_verilog.clone().unwrap());
             return;
         }
@@ -1133,6 +2304,8 @@ impl ModDef {
                     file,
                     leaf_text,
                     enum_remapping,
+                    parameter_info,
+                    comment_info,
                 );
             }
         }
@@ -1141,6 +2314,39 @@ impl ModDef {
 
         let mut module = file.add_module(&core.name);
 
+        // Parameters of imported modules are recorded for reference only;
+        // stubs of these modules are emitted without them, as before.
+        if !core.parameters.is_empty() && core.verilog_import.is_none() {
+            parameter_info.declarations.insert(
+                core.name.clone(),
+                core.parameters
+                    .iter()
+                    .map(|(name, (ty, default))| {
+                        if ty.is_empty() {
+                            format!("parameter {} = {}", name, default)
+                        } else {
+                            format!("parameter {} {} = {}", ty, name, default)
+                        }
+                    })
+                    .collect(),
+            );
+        }
+
+        if !core.port_width_exprs.is_empty() {
+            parameter_info.width_exprs.insert(
+                core.name.clone(),
+                core.port_width_exprs
+                    .iter()
+                    .map(|(name, expr)| {
+                        (
+                            name.clone(),
+                            (core.ports.get(name).unwrap().width(), expr.clone()),
+                        )
+                    })
+                    .collect(),
+            );
+        }
+
         let mut ports: IndexMap<String, LogicRef> = IndexMap::new();
 
         for port_name in core.ports.keys() {
@@ -1213,7 +2419,7 @@ connectivity on that net will need to be updated.",
                         inst_name, port_name, core.name, net_name);
                 }
 
-                if inst.borrow().enum_ports.contains_key(port_name) {
+                if inst.borrow().enum_ports.contains_key(port_name) && matches!(io, IO::Input(_)) {
                     enum_remapping
                         .entry(core.name.clone())
                         .or_default()
@@ -1257,6 +2463,22 @@ alternate net name to connect_to_net().",
             let mut connection_port_names = Vec::new();
             let mut connection_expressions = Vec::new();
 
+            if let Some(comment) = core.inst_comments.get(inst_name) {
+                comment_info
+                    .instances
+                    .entry(core.name.clone())
+                    .or_default()
+                    .insert(inst_name.clone(), comment.clone());
+            }
+
+            if let Some(overrides) = core.inst_parameters.get(inst_name) {
+                parameter_info
+                    .overrides
+                    .entry(core.name.clone())
+                    .or_default()
+                    .insert(inst_name.clone(), overrides.clone());
+            }
+
             for (port_name, io) in inst.borrow().ports.iter() {
                 connection_port_names.push(port_name.clone());
 
@@ -1472,6 +2694,7 @@ since the width of that port is {}. Check the slice indices for this instance po
                             .to_expr(),
                         width: lhs.width(),
                         depth: pipeline.depth,
+                        omit_out_stages: core.omit_pipeline_out_stages,
                         pipe_in: &rhs_slice.to_expr(),
                         pipe_out: &lhs_slice.to_expr(),
                     };
@@ -1620,104 +2843,886 @@ since the width of that port is {}. Check the slice indices for this instance po
             name.as_ref()
         );
 
-        self.def_intf(name, mapping)
+        self.def_intf(name, mapping)
+    }
+
+    pub fn def_intf_from_regex(
+        &self,
+        name: impl AsRef<str>,
+        search: impl AsRef<str>,
+        replace: impl AsRef<str>,
+    ) -> Intf {
+        self.def_intf_from_regexes(name, &[(search.as_ref(), replace.as_ref())])
+    }
+
+    pub fn def_intf_from_regexes(&self, name: impl AsRef<str>, regexes: &[(&str, &str)]) -> Intf {
+        let mut mapping = IndexMap::new();
+        let regexes = regexes
+            .iter()
+            .map(|(search, replace)| {
+                (
+                    Regex::new(search).expect("Failed to compile regex"),
+                    replace,
+                )
+            })
+            .collect::<Vec<_>>();
+        {
+            let core = self.core.borrow();
+            for port_name in core.ports.keys() {
+                for (regex, replace) in &regexes {
+                    if regex.is_match(port_name) {
+                        let func_name = regex.replace(port_name, **replace).to_string();
+                        let port = self.get_port(port_name);
+                        mapping.insert(func_name, (port_name.clone(), port.io().width() - 1, 0));
+                        break;
+                    }
+                }
+            }
+        }
+
+        assert!(
+            !mapping.is_empty(),
+            "Empty interface definition for {}.{}",
+            self.get_name(),
+            name.as_ref()
+        );
+
+        self.def_intf(name, mapping)
+    }
+
+    /// Returns the interface with the given name; panics if an interface with
+    /// that name does not exist.
+    pub fn get_intf(&self, name: impl AsRef<str>) -> Intf {
+        self.try_get_intf(name.as_ref()).unwrap_or_else(|| {
+            panic!(
+                "Interface '{}' does not exist in module '{}'",
+                name.as_ref(),
+                self.core.borrow().name
+            )
+        })
+    }
+
+    /// Returns the interface on this module definition with the given name, or
+    /// `None` if an interface with that name does not exist.
+    pub fn try_get_intf(&self, name: impl AsRef<str>) -> Option<Intf> {
+        if self.core.borrow().interfaces.contains_key(name.as_ref()) {
+            Some(Intf::ModDef {
+                name: name.as_ref().to_string(),
+                mod_def_core: Rc::downgrade(&self.core),
+            })
+        } else {
+            None
+        }
+    }
+
+    /// For each port on each instance within this module definition that has
+    /// no connections, tieoffs, or unused markings, creates a port on this
+    /// module definition called `<prefix><instance name>_<port name>` with the
+    /// same width and direction, and connects the two. Returns the new ports.
+    pub fn export_all_unconnected(&self, prefix: impl AsRef<str>) -> Vec<Port> {
+        let unconnected: Vec<Port> = {
+            let core = self.core.borrow();
+            self.get_instances()
+                .iter()
+                .flat_map(|inst| inst.get_ports(None))
+                .filter(|port| !port.to_port_slice().has_connections(&core))
+                .collect()
+        };
+
+        unconnected
+            .iter()
+            .map(|port| {
+                let Port::ModInst {
+                    inst_name,
+                    port_name,
+                    ..
+                } = port
+                else {
+                    unreachable!()
+                };
+                port.export_as(format!("{}{}_{}", prefix.as_ref(), inst_name, port_name))
+            })
+            .collect()
+    }
+
+    /// Adds a port to this module definition with the same name, width, and
+    /// direction as the port `port_name` on the instance `inst`, and connects
+    /// the two, returning the new port. This is equivalent to calling
+    /// `export()` on the instance port, but checks that `inst` is an instance
+    /// within this module definition. Panics if it is not, or if the instance
+    /// port does not exist.
+    pub fn add_wrapper_port_and_connect(&self, inst: &ModInst, port_name: impl AsRef<str>) -> Port {
+        if !Rc::ptr_eq(&inst.mod_def_core.upgrade().unwrap(), &self.core) {
+            panic!(
+                "Instance {} is not an instance within module definition {}",
+                inst.debug_string(),
+                self.get_name()
+            );
+        }
+
+        inst.get_port(port_name).export()
+    }
+
+    /// Punches a feedthrough through this module definition with the given
+    /// input and output names and width. This will create two new ports on the
+    /// module definition, `input_name[width-1:0]` and `output_name[width-1:0]`,
+    /// and connect them together.
+    pub fn feedthrough(
+        &self,
+        input_name: impl AsRef<str>,
+        output_name: impl AsRef<str>,
+        width: usize,
+    ) {
+        self.feedthrough_generic(input_name, output_name, width, None);
+    }
+
+    pub fn feedthrough_pipeline(
+        &self,
+        input_name: impl AsRef<str>,
+        output_name: impl AsRef<str>,
+        width: usize,
+        pipeline: PipelineConfig,
+    ) {
+        self.feedthrough_generic(input_name, output_name, width, Some(pipeline));
+    }
+
+    /// Same as `feedthrough()`, but returns the newly created ports as a tuple
+    /// of `(input_port, output_port)`, named exactly `in_name` and `out_name`.
+    pub fn add_feedthrough_named(
+        &self,
+        in_name: impl AsRef<str>,
+        out_name: impl AsRef<str>,
+        width: usize,
+    ) -> (Port, Port) {
+        self.feedthrough_generic(in_name, out_name, width, None)
+    }
+
+    fn feedthrough_generic(
+        &self,
+        input_name: impl AsRef<str>,
+        output_name: impl AsRef<str>,
+        width: usize,
+        pipeline: Option<PipelineConfig>,
+    ) -> (Port, Port) {
+        let input_port = self.add_port(input_name, IO::Input(width));
+        let output_port = self.add_port(output_name, IO::Output(width));
+        input_port.connect_generic(&output_port, pipeline);
+        (input_port, output_port)
+    }
+
+    /// Returns a summary of the connectivity of each port on this module
+    /// definition, in port declaration order. For each port, the summary
+    /// reports the total number of bits, as well as how many of those bits are
+    /// connected to something within this module definition, tied off, or
+    /// marked as unused.
+    pub fn get_port_connections_summary(&self) -> Vec<PortConnectionSummary> {
+        let core = self.core.borrow();
+
+        let mut connected: IndexMap<String, BigUint> = IndexMap::new();
+        let mut tiedoff: IndexMap<String, BigUint> = IndexMap::new();
+        let mut unused: IndexMap<String, BigUint> = IndexMap::new();
+
+        fn mark(bits: &mut IndexMap<String, BigUint>, slice: &PortSlice) {
+            if let Port::ModDef { name, .. } = &slice.port {
+                *bits.entry(name.clone()).or_default() |= slice.mask();
+            }
+        }
+
+        for Assignment { lhs, rhs, .. } in &core.assignments {
+            mark(&mut connected, lhs);
+            mark(&mut connected, rhs);
+        }
+        for inst_connections in core.inst_connections.values() {
+            for connections in inst_connections.values() {
+                for inst_connection in connections {
+                    if let PortSliceOrWire::PortSlice(slice) = &inst_connection.connected_to {
+                        mark(&mut connected, slice);
+                    }
+                }
+            }
+        }
+        for (slice, _) in &core.tieoffs {
+            mark(&mut tiedoff, slice);
+        }
+        for slice in &core.unused {
+            mark(&mut unused, slice);
+        }
+
+        let count = |bits: &IndexMap<String, BigUint>, port_name: &String| {
+            bits.get(port_name).map_or(0, |b| b.count_ones() as usize)
+        };
+
+        core.ports
+            .iter()
+            .map(|(port_name, io)| PortConnectionSummary {
+                port_name: port_name.clone(),
+                total_bits: io.width(),
+                connected_bits: count(&connected, port_name),
+                tiedoff_bits: count(&tiedoff, port_name),
+                unused_bits: count(&unused, port_name),
+            })
+            .collect()
+    }
+
+    /// Returns the bit-level connectivity within this module definition as a
+    /// list of `(driver_port, driver_bit, load_port, load_bit)` tuples, with
+    /// one tuple for each pair of connected bits. Pipelined connections are
+    /// reported as if they were direct connections, and tieoffs are not
+    /// reported since they have no driver port. An instance inout port
+    /// connected to another port is reported as the driver of that port.
+    pub fn get_connectivity_matrix(&self) -> Vec<(PortKey, usize, PortKey, usize)> {
+        self.bit_connections(false)
+    }
+
+    /// Returns the names of the module definitions of all instances whose
+    /// outputs drive inputs of the instance `inst_name`, based on the current
+    /// connections within this module definition. Each module definition name
+    /// is listed once, in the order in which connections were made. Panics if
+    /// the instance does not exist.
+    pub fn get_driven_by(&self, inst_name: impl AsRef<str>) -> Vec<String> {
+        if !self
+            .core
+            .borrow()
+            .instances
+            .contains_key(inst_name.as_ref())
+        {
+            panic!(
+                "Instance {}.{} does not exist",
+                self.get_name(),
+                inst_name.as_ref()
+            );
+        }
+
+        let mut result = Vec::new();
+        for (driver, _, load, _) in self.get_connectivity_matrix() {
+            if let (
+                PortKey::ModInstPort {
+                    inst_name: driver_inst_name,
+                    ..
+                },
+                PortKey::ModInstPort {
+                    inst_name: load_inst_name,
+                    ..
+                },
+            ) = (driver, load)
+            {
+                if load_inst_name == inst_name.as_ref() {
+                    let mod_def_name = self.core.borrow().instances[&driver_inst_name]
+                        .borrow()
+                        .name
+                        .clone();
+                    if !result.contains(&mod_def_name) {
+                        result.push(mod_def_name);
+                    }
+                }
+            }
+        }
+        result
+    }
+
+    /// Implements `get_connectivity_matrix()`. If `combinational_only` is
+    /// `true`, connections with pipeline stages and connections involving
+    /// inout ports are left out.
+    fn bit_connections(&self, combinational_only: bool) -> Vec<(PortKey, usize, PortKey, usize)> {
+        let core = self.core.borrow();
+
+        let mut result = Vec::new();
+
+        let mut add = |driver: &PortSlice, load: &PortSlice| {
+            if combinational_only
+                && (matches!(driver.port.io(), IO::InOut(_))
+                    || matches!(load.port.io(), IO::InOut(_)))
+            {
+                return;
+            }
+            let driver_key = driver.port.to_port_key();
+            let load_key = load.port.to_port_key();
+            for i in 0..driver.width() {
+                result.push((
+                    driver_key.clone(),
+                    driver.lsb + i,
+                    load_key.clone(),
+                    load.lsb + i,
+                ));
+            }
+        };
+
+        for Assignment { lhs, rhs, pipeline } in &core.assignments {
+            if combinational_only && pipeline.as_ref().is_some_and(|p| p.depth > 0) {
+                continue;
+            }
+            add(rhs, lhs);
+        }
+
+        let mut nets: IndexMap<String, Vec<&PortSlice>> = IndexMap::new();
+        for inst_connections in core.inst_connections.values() {
+            for connections in inst_connections.values() {
+                for inst_connection in connections {
+                    let inst_slice = &inst_connection.inst_port_slice;
+                    match &inst_connection.connected_to {
+                        PortSliceOrWire::PortSlice(other) => match inst_slice.port.io() {
+                            IO::Input(_) => add(other, inst_slice),
+                            IO::Output(_) | IO::InOut(_) => add(inst_slice, other),
+                        },
+                        PortSliceOrWire::Wire(wire) => {
+                            nets.entry(wire.name.clone()).or_default().push(inst_slice)
+                        }
+                    }
+                }
+            }
+        }
+
+        for slices in nets.values() {
+            for driver in slices
+                .iter()
+                .filter(|slice| !matches!(slice.port.io(), IO::Input(_)))
+            {
+                for load in slices
+                    .iter()
+                    .filter(|slice| matches!(slice.port.io(), IO::Input(_)))
+                {
+                    add(driver, load);
+                }
+            }
+        }
+
+        result
+    }
+
+    /// Checks for combinational loops in the hierarchy below this module
+    /// definition, following unpipelined connections (including connections
+    /// with zero pipeline stages) bit by bit through instances and
+    /// feedthroughs. Instances of module definitions without connections of
+    /// their own (e.g., stubs and imported modules) are assumed to have no
+    /// combinational paths from inputs to outputs, and connections involving
+    /// inout ports are ignored. Returns `Ok(())` if no loops are found;
+    /// otherwise returns one loop per set of connected ports, each as the
+    /// ordered list of port bits along the loop, named hierarchically, e.g.
+    /// `Top.a.x[0]`.
+    pub fn check_combinational_loops(&self) -> Result<(), Vec<Vec<String>>> {
+        let mut graph: IndexMap<String, Vec<String>> = IndexMap::new();
+        self.add_combinational_edges(&self.get_name(), &mut graph);
+
+        #[derive(Clone, Copy, PartialEq)]
+        enum State {
+            Visiting,
+            Done,
+        }
+
+        fn visit<'a>(
+            node: &'a str,
+            graph: &'a IndexMap<String, Vec<String>>,
+            state: &mut HashMap<&'a str, State>,
+            stack: &mut Vec<&'a str>,
+            loops: &mut Vec<Vec<String>>,
+        ) {
+            state.insert(node, State::Visiting);
+            stack.push(node);
+            for next in graph.get(node).into_iter().flatten() {
+                match state.get(next.as_str()) {
+                    None => visit(next, graph, state, stack, loops),
+                    Some(State::Visiting) => {
+                        let start = stack.iter().position(|n| *n == next).unwrap();
+                        loops.push(stack[start..].iter().map(|n| n.to_string()).collect());
+                    }
+                    Some(State::Done) => {}
+                }
+            }
+            stack.pop();
+            state.insert(node, State::Done);
+        }
+
+        let mut state = HashMap::new();
+        let mut loops = Vec::new();
+        for node in graph.keys() {
+            if !state.contains_key(node.as_str()) {
+                visit(node, &graph, &mut state, &mut Vec::new(), &mut loops);
+            }
+        }
+
+        // Each bit of a multi-bit connection forms its own loop, so only keep
+        // the first loop through any given sequence of ports.
+        let mut seen = HashSet::new();
+        loops.retain(|path| {
+            let ports: Vec<&str> = path
+                .iter()
+                .map(|node| node.rsplit_once('[').unwrap().0)
+                .collect();
+            seen.insert(ports.join(" "))
+        });
+
+        if loops.is_empty() {
+            Ok(())
+        } else {
+            Err(loops)
+        }
+    }
+
+    /// Adds the combinational connections within this module definition and
+    /// its instances, recursively, to `graph`, which maps each port bit to
+    /// the port bits that it drives. `path` is the hierarchical name of this
+    /// module definition.
+    fn add_combinational_edges(&self, path: &str, graph: &mut IndexMap<String, Vec<String>>) {
+        let node = |key: &PortKey, bit: usize| match key {
+            PortKey::ModDefPort { port_name, .. } => format!("{}.{}[{}]", path, port_name, bit),
+            PortKey::ModInstPort {
+                inst_name,
+                port_name,
+                ..
+            } => format!("{}.{}.{}[{}]", path, inst_name, port_name, bit),
+        };
+
+        for (driver, driver_bit, load, load_bit) in self.bit_connections(true) {
+            graph
+                .entry(node(&driver, driver_bit))
+                .or_default()
+                .push(node(&load, load_bit));
+        }
+
+        let instances: Vec<(String, ModDef)> = self
+            .core
+            .borrow()
+            .instances
+            .iter()
+            .map(|(inst_name, core)| (inst_name.clone(), ModDef { core: core.clone() }))
+            .collect();
+        for (inst_name, mod_def) in instances {
+            mod_def.add_combinational_edges(&format!("{}.{}", path, inst_name), graph);
+        }
+    }
+
+    /// Returns all simple paths of signals from instance `from` to instance
+    /// `to` within this module definition, shortest first. Each path is the
+    /// sequence of nets traversed, where an instance output connected to an
+    /// instance input is named `<inst>_<port>` after the driving instance
+    /// port (as in the emitted Verilog), and nets created via
+    /// `connect_to_net()` keep their own names. Since the contents of
+    /// instances are not inspected, a path may enter an instance through any
+    /// input and leave it through any output. Panics if either instance is
+    /// not within this module definition.
+    pub fn find_signal_paths(&self, from: &ModInst, to: &ModInst) -> Vec<Vec<String>> {
+        for inst in [from, to] {
+            if !Rc::ptr_eq(&inst.mod_def_core.upgrade().unwrap(), &self.core) {
+                panic!(
+                    "Instance {} is not an instance within module definition {}",
+                    inst.debug_string(),
+                    self.get_name()
+                );
+            }
+        }
+
+        let core = self.core.borrow();
+
+        // Map from instance name to (net name, destination instance name) edges.
+        let mut edges: IndexMap<String, Vec<(String, String)>> = IndexMap::new();
+        let mut add_edge = |from_inst: &str, net: String, to_inst: &str| {
+            let inst_edges = edges.entry(from_inst.to_string()).or_default();
+            let edge = (net, to_inst.to_string());
+            if !inst_edges.contains(&edge) {
+                inst_edges.push(edge);
+            }
+        };
+
+        for Assignment { lhs, rhs, .. } in &core.assignments {
+            if let (
+                Port::ModInst {
+                    inst_name: lhs_inst,
+                    ..
+                },
+                Port::ModInst {
+                    inst_name: rhs_inst,
+                    port_name: rhs_port,
+                    ..
+                },
+            ) = (&lhs.port, &rhs.port)
+            {
+                add_edge(rhs_inst, format!("{}_{}", rhs_inst, rhs_port), lhs_inst);
+            }
+        }
+
+        let mut nets: IndexMap<String, Vec<&PortSlice>> = IndexMap::new();
+        for inst_connections in core.inst_connections.values() {
+            for connections in inst_connections.values() {
+                for inst_connection in connections {
+                    if let PortSliceOrWire::Wire(wire) = &inst_connection.connected_to {
+                        nets.entry(wire.name.clone())
+                            .or_default()
+                            .push(&inst_connection.inst_port_slice);
+                    }
+                }
+            }
+        }
+        for (net_name, slices) in &nets {
+            for driver in slices.iter().filter(|slice| ModDef::can_drive(slice)) {
+                for load in slices.iter().filter(|slice| ModDef::can_be_driven(slice)) {
+                    if let (
+                        Port::ModInst {
+                            inst_name: driver_inst,
+                            ..
+                        },
+                        Port::ModInst {
+                            inst_name: load_inst,
+                            ..
+                        },
+                    ) = (&driver.port, &load.port)
+                    {
+                        if driver_inst != load_inst {
+                            add_edge(driver_inst, net_name.clone(), load_inst);
+                        }
+                    }
+                }
+            }
+        }
+
+        // Breadth-first search over partial paths, each of which is a list of
+        // visited instances and the nets traversed between them.
+        let mut result = Vec::new();
+        let mut queue: VecDeque<(Vec<&str>, Vec<String>)> = VecDeque::new();
+        queue.push_back((vec![from.name.as_str()], Vec::new()));
+        while let Some((insts, path)) = queue.pop_front() {
+            let last = *insts.last().unwrap();
+            if last == to.name && !path.is_empty() {
+                result.push(path);
+                continue;
+            }
+            for (net, next) in edges.get(last).into_iter().flatten() {
+                if insts.contains(&next.as_str()) {
+                    continue;
+                }
+                let mut next_insts = insts.clone();
+                next_insts.push(next);
+                let mut next_path = path.clone();
+                next_path.push(net.clone());
+                queue.push_back((next_insts, next_path));
+            }
+        }
+
+        result
+    }
+
+    /// Returns the maximum total pipeline depth along any path from an input
+    /// of this module definition to the output `output_port`, summing the
+    /// depths of pipelined connections along each path. Paths are followed
+    /// into instances of module definitions that have connections of their
+    /// own; other instances (e.g., stubs and imported modules) are assumed to
+    /// connect each of their inputs to each of their outputs without pipeline
+    /// stages. Returns 0 if the output is not reachable from any input. Panics
+    /// if `output_port` is not an output of this module definition.
+    pub fn get_max_pipeline_depth_to_output(&self, output_port: impl AsRef<str>) -> usize {
+        if !matches!(self.get_port(output_port.as_ref()).io(), IO::Output(_)) {
+            panic!(
+                "Port {}.{} is not an output",
+                self.get_name(),
+                output_port.as_ref()
+            );
+        }
+
+        self.pipeline_depths_to(
+            None,
+            output_port.as_ref(),
+            &mut PipelineDepthCache::default(),
+        )
+        .into_values()
+        .max()
+        .unwrap_or(0)
     }
 
-    pub fn def_intf_from_regex(
+    /// Returns a map from the name of each input of this module definition
+    /// that reaches the given port to the maximum pipeline depth along the way.
+    /// The port is either an output of this module definition (`inst_name` is
+    /// `None`) or an input of the instance `inst_name`. Results are memoized in
+    /// `cache`, so each node is visited once.
+    fn pipeline_depths_to(
         &self,
-        name: impl AsRef<str>,
-        search: impl AsRef<str>,
-        replace: impl AsRef<str>,
-    ) -> Intf {
-        self.def_intf_from_regexes(name, &[(search.as_ref(), replace.as_ref())])
-    }
+        inst_name: Option<&str>,
+        port_name: &str,
+        cache: &mut PipelineDepthCache,
+    ) -> IndexMap<String, usize> {
+        let core_ptr = Rc::as_ptr(&self.core);
+        let node = (
+            core_ptr,
+            inst_name.map(|s| s.to_string()),
+            port_name.to_string(),
+        );
+        if let Some(result) = cache.depths.get(&node) {
+            return result.clone();
+        }
 
-    pub fn def_intf_from_regexes(&self, name: impl AsRef<str>, regexes: &[(&str, &str)]) -> Intf {
-        let mut mapping = IndexMap::new();
-        let regexes = regexes
-            .iter()
-            .map(|(search, replace)| {
-                (
-                    Regex::new(search).expect("Failed to compile regex"),
-                    replace,
-                )
-            })
-            .collect::<Vec<_>>();
-        {
+        let mut result: IndexMap<String, usize> = IndexMap::new();
+        if !cache.visiting.insert(node.clone()) {
+            return result;
+        }
+
+        let is_opaque = {
             let core = self.core.borrow();
-            for port_name in core.ports.keys() {
-                for (regex, replace) in &regexes {
-                    if regex.is_match(port_name) {
-                        let func_name = regex.replace(port_name, **replace).to_string();
-                        let port = self.get_port(port_name);
-                        mapping.insert(func_name, (port_name.clone(), port.io().width() - 1, 0));
-                        break;
-                    }
+            core.assignments.is_empty()
+                && core.instances.is_empty()
+                && core.inst_connections.is_empty()
+        };
+        if inst_name.is_none() && is_opaque {
+            for (name, io) in self.core.borrow().ports.iter() {
+                if matches!(io, IO::Input(_)) {
+                    result.insert(name.clone(), 0);
                 }
             }
+            cache.visiting.remove(&node);
+            cache.depths.insert(node, result.clone());
+            return result;
         }
 
-        assert!(
-            !mapping.is_empty(),
-            "Empty interface definition for {}.{}",
-            self.get_name(),
-            name.as_ref()
-        );
+        let drivers = cache
+            .drivers
+            .entry(core_ptr)
+            .or_insert_with(|| self.pipeline_drivers())
+            .get(&(node.1.clone(), node.2.clone()))
+            .cloned()
+            .unwrap_or_default();
 
-        self.def_intf(name, mapping)
+        fn update(result: &mut IndexMap<String, usize>, name: String, depth: usize) {
+            let entry = result.entry(name).or_insert(depth);
+            *entry = (*entry).max(depth);
+        }
+
+        for (driver, depth) in drivers {
+            match &driver {
+                Port::ModDef { name, .. } => {
+                    if matches!(driver.io(), IO::Input(_)) {
+                        update(&mut result, name.clone(), depth);
+                    }
+                }
+                Port::ModInst {
+                    inst_name: driver_inst_name,
+                    port_name: driver_port_name,
+                    ..
+                } => {
+                    let child = ModDef {
+                        core: self.core.borrow().instances[driver_inst_name].clone(),
+                    };
+                    let child_depths = child.pipeline_depths_to(None, driver_port_name, cache);
+                    for (child_input, child_depth) in child_depths {
+                        let upstream =
+                            self.pipeline_depths_to(Some(driver_inst_name), &child_input, cache);
+                        for (input, upstream_depth) in upstream {
+                            update(&mut result, input, upstream_depth + child_depth + depth);
+                        }
+                    }
+                }
+            }
+        }
+
+        cache.visiting.remove(&node);
+        cache.depths.insert(node, result.clone());
+        result
     }
 
-    /// Returns the interface with the given name; panics if an interface with
-    /// that name does not exist.
-    pub fn get_intf(&self, name: impl AsRef<str>) -> Intf {
+    /// Returns the drivers of each node of this module definition, as
+    /// described in `pipeline_depths_to()`.
+    fn pipeline_drivers(&self) -> PipelineDrivers {
+        fn node_of(port: &Port) -> (Option<String>, String) {
+            match port {
+                Port::ModDef { name, .. } => (None, name.clone()),
+                Port::ModInst {
+                    inst_name,
+                    port_name,
+                    ..
+                } => (Some(inst_name.clone()), port_name.clone()),
+            }
+        }
+
+        let mut result = PipelineDrivers::new();
         let core = self.core.borrow();
-        if core.interfaces.contains_key(name.as_ref()) {
-            Intf::ModDef {
-                name: name.as_ref().to_string(),
-                mod_def_core: Rc::downgrade(&self.core),
+
+        for Assignment { lhs, rhs, pipeline } in &core.assignments {
+            result
+                .entry(node_of(&lhs.port))
+                .or_default()
+                .push((rhs.port.clone(), pipeline.as_ref().map_or(0, |p| p.depth)));
+        }
+
+        let inst_connections = core
+            .inst_connections
+            .values()
+            .flat_map(|port_connections| port_connections.values())
+            .flatten();
+
+        // Instance outputs connected to each wire
+        let mut wire_drivers: HashMap<&str, Vec<&Port>> = HashMap::new();
+        for inst_connection in inst_connections.clone() {
+            if let PortSliceOrWire::Wire(wire) = &inst_connection.connected_to {
+                if matches!(inst_connection.inst_port_slice.port.io(), IO::Output(_)) {
+                    wire_drivers
+                        .entry(wire.name.as_str())
+                        .or_default()
+                        .push(&inst_connection.inst_port_slice.port);
+                }
             }
-        } else {
+        }
+
+        for inst_connection in inst_connections {
+            if let PortSliceOrWire::Wire(wire) = &inst_connection.connected_to {
+                if let Some(drivers) = wire_drivers.get(wire.name.as_str()) {
+                    result
+                        .entry(node_of(&inst_connection.inst_port_slice.port))
+                        .or_default()
+                        .extend(drivers.iter().map(|driver| ((*driver).clone(), 0)));
+                }
+            }
+        }
+
+        result
+    }
+
+    /// Registers the boundary of this module definition by adding `depth`
+    /// pipeline stages, clocked by the input port `clk`, to each connection
+    /// of an input or output port of this module definition: inputs are
+    /// registered after entering the module and outputs before leaving it. A
+    /// direct connection from an input to an output therefore gets `2 *
+    /// depth` stages, and a connection that is already pipelined on `clk`
+    /// gets `depth` additional stages. Since registers are added per
+    /// connection, an input that fans out to several loads gets a separate
+    /// set of registers for each load. The clock port, inout ports, and
+    /// tieoffs are skipped. Returns the ports that were registered, in
+    /// declaration order. Panics without modifying this module definition if
+    /// any of the connections is already pipelined on a different clock.
+    pub fn register_boundary(&self, clk: impl AsRef<str>, depth: usize) -> Vec<Port> {
+        if self.frozen() {
             panic!(
-                "Interface '{}' does not exist in module '{}'",
-                name.as_ref(),
-                core.name
+                "Module {} is frozen. wrap() first if modifications are needed.",
+                self.get_name()
             );
         }
-    }
 
-    /// Punches a feedthrough through this module definition with the given
-    /// input and output names and width. This will create two new ports on the
-    /// module definition, `input_name[width-1:0]` and `output_name[width-1:0]`,
-    /// and connect them together.
-    pub fn feedthrough(
-        &self,
-        input_name: impl AsRef<str>,
-        output_name: impl AsRef<str>,
-        width: usize,
-    ) {
-        self.feedthrough_generic(input_name, output_name, width, None);
+        let clk = clk.as_ref();
+        if !matches!(self.get_port(clk).io(), IO::Input(_)) {
+            panic!("Clock {}.{} is not an input", self.get_name(), clk);
+        }
+
+        let registered = {
+            let mut core = self.core.borrow_mut();
+
+            // Determine the number of stages to add to each assignment,
+            // checking all of them before any are modified.
+            let mut registered: HashSet<String> = HashSet::new();
+            let mut stages_to_add: Vec<(usize, usize)> = Vec::new();
+            for (index, Assignment { lhs, rhs, pipeline }) in core.assignments.iter().enumerate() {
+                let mut stages = 0;
+                for slice in [lhs, rhs] {
+                    if let Port::ModDef { name, .. } = &slice.port {
+                        if name != clk && !matches!(core.ports[name], IO::InOut(_)) {
+                            stages += depth;
+                            registered.insert(name.clone());
+                        }
+                    }
+                }
+                if stages == 0 {
+                    continue;
+                }
+                if let Some(existing) = pipeline {
+                    if existing.clk != clk {
+                        panic!(
+                            "Cannot register connection of {}.{} on clock {}: it is already pipelined on clock {}",
+                            core.name,
+                            lhs.port.get_port_name(),
+                            clk,
+                            existing.clk
+                        );
+                    }
+                }
+                stages_to_add.push((index, stages));
+            }
+
+            for (index, stages) in stages_to_add {
+                let pipeline = &mut core.assignments[index].pipeline;
+                match pipeline {
+                    Some(existing) => existing.depth += stages,
+                    None => {
+                        *pipeline = Some(PipelineConfig {
+                            clk: clk.to_string(),
+                            depth: stages,
+                        })
+                    }
+                }
+            }
+
+            registered
+        };
+
+        self.get_ports(None)
+            .into_iter()
+            .filter(|port| registered.contains(port.name()))
+            .collect()
     }
 
-    pub fn feedthrough_pipeline(
+    /// Inserts a pipeline module between two instances in this module
+    /// definition that are connected via interfaces called `intf_name`. Any
+    /// direct connections between the two interfaces are removed, and a new
+    /// module definition called
+    /// `<mod_def_name>_<a_inst_name>_<b_inst_name>_<intf_name>_pipeline` is
+    /// created with a pipelined feedthrough for each function of the
+    /// interface. This module is instantiated as
+    /// `<a_inst_name>_<b_inst_name>_<intf_name>_pipeline_i`, and the interface
+    /// on `a_inst` is connected to the interface on `b_inst` through it. The
+    /// pipeline clock is connected to the port on this module definition
+    /// named `pipeline.clk`, which is created if it does not already exist.
+    /// Returns the pipeline module instance.
+    pub fn wrap_with_pipeline(
         &self,
-        input_name: impl AsRef<str>,
-        output_name: impl AsRef<str>,
-        width: usize,
+        a_inst: &ModInst,
+        b_inst: &ModInst,
+        intf_name: &str,
         pipeline: PipelineConfig,
-    ) {
-        self.feedthrough_generic(input_name, output_name, width, Some(pipeline));
-    }
+    ) -> ModInst {
+        for inst in [a_inst, b_inst] {
+            if !Rc::ptr_eq(&inst.mod_def_core.upgrade().unwrap(), &self.core) {
+                panic!(
+                    "Cannot insert pipeline: instance {} is not in module {}",
+                    inst.debug_string(),
+                    self.get_name()
+                );
+            }
+        }
 
-    fn feedthrough_generic(
-        &self,
-        input_name: impl AsRef<str>,
-        output_name: impl AsRef<str>,
-        width: usize,
-        pipeline: Option<PipelineConfig>,
-    ) {
-        let input_port = self.add_port(input_name, IO::Input(width));
-        let output_port = self.add_port(output_name, IO::Output(width));
-        input_port.connect_generic(&output_port, pipeline);
+        let a_intf = a_inst.get_intf(intf_name);
+        let b_intf = b_inst.get_intf(intf_name);
+
+        // Remove direct connections between the two interfaces.
+        let a_slices: Vec<PortSlice> = a_intf.get_port_slices().into_values().collect();
+        let b_slices: Vec<PortSlice> = b_intf.get_port_slices().into_values().collect();
+        self.core.borrow_mut().assignments.retain(|assignment| {
+            let lhs_in_a = a_slices.iter().any(|s| s.overlaps(&assignment.lhs));
+            let lhs_in_b = b_slices.iter().any(|s| s.overlaps(&assignment.lhs));
+            let rhs_in_a = a_slices.iter().any(|s| s.overlaps(&assignment.rhs));
+            let rhs_in_b = b_slices.iter().any(|s| s.overlaps(&assignment.rhs));
+            !((lhs_in_a && rhs_in_b) || (lhs_in_b && rhs_in_a))
+        });
+
+        // Create the pipeline module definition.
+        let pipeline_def = ModDef::new(format!(
+            "{}_{}_{}_{}_pipeline",
+            self.get_name(),
+            a_inst.name,
+            b_inst.name,
+            intf_name
+        ));
+        pipeline_def.add_port(&pipeline.clk, IO::Input(1));
+        pipeline_def.set_omit_pipeline_out_stages(self.core.borrow().omit_pipeline_out_stages);
+        a_intf.feedthrough_pipeline(&pipeline_def, &a_inst.name, &b_inst.name, pipeline.clone());
+
+        // Instantiate the pipeline and wire it up.
+        let pipeline_inst_name =
+            format!("{}_{}_{}_pipeline_i", a_inst.name, b_inst.name, intf_name);
+        let pipeline_inst = self.instantiate(&pipeline_def, Some(&pipeline_inst_name), None);
+
+        if !self.has_port(&pipeline.clk) {
+            self.add_port(&pipeline.clk, IO::Input(1));
+        }
+        self.get_port(&pipeline.clk)
+            .connect(&pipeline_inst.get_port(&pipeline.clk));
+
+        a_intf.connect(&pipeline_inst.get_intf(&a_inst.name), false);
+        pipeline_inst.get_intf(&b_inst.name).connect(&b_intf, false);
+
+        pipeline_inst
     }
 
     /// Instantiates this module definition within a new module definition, and
@@ -1979,6 +3984,14 @@ since the width of that port is {}. Check the slice indices for this instance po
                 verilog_import: None,
                 inst_connections: IndexMap::new(),
                 reserved_net_definitions: IndexMap::new(),
+                parameters: IndexMap::new(),
+                port_width_exprs: IndexMap::new(),
+                port_timing_constraints: IndexMap::new(),
+                comment: None,
+                inst_parameters: IndexMap::new(),
+                inst_comments: IndexMap::new(),
+                allow_enum_mismatch: true,
+                omit_pipeline_out_stages: false,
             })),
         }
     }
@@ -2174,11 +4187,11 @@ since the width of that port is {}. Check the slice indices for this instance po
             // check directionality
 
             if !Self::can_be_driven(lhs_slice) {
-                panic!("{} cannot be driven.", lhs_slice.debug_string());
+                Self::panic_inconsistent_direction(lhs_slice, "cannot be driven", &mod_def_core);
             }
 
             if !Self::can_drive(rhs_slice) {
-                panic!("{} cannot drive.", rhs_slice.debug_string());
+                Self::panic_inconsistent_direction(rhs_slice, "cannot drive", &mod_def_core);
             }
 
             // check that widths match
@@ -2192,6 +4205,9 @@ since the width of that port is {}. Check the slice indices for this instance po
                 );
             }
 
+            // check that enum types match
+            Self::check_enum_types(lhs_slice, rhs_slice, mod_def_core.allow_enum_mismatch);
+
             let lhs_key = lhs_slice.port.to_port_key();
             let rhs_key = rhs_slice.port.to_port_key();
 
@@ -2231,6 +4247,10 @@ since the width of that port is {}. Check the slice indices for this instance po
 
         // process instance connections
 
+        // Map from net name to the first instance port slice connected to that
+        // net, used to check enum type consistency.
+        let mut net_first_slices: IndexMap<String, &PortSlice> = IndexMap::new();
+
         for inst_connections in mod_def_core.inst_connections.values() {
             for connections in inst_connections.values() {
                 for inst_connection in connections {
@@ -2262,6 +4282,25 @@ since the width of that port is {}. Check the slice indices for this instance po
                         );
                     }
 
+                    // check that enum types match
+                    match &inst_connection.connected_to {
+                        PortSliceOrWire::PortSlice(other_slice) => Self::check_enum_types(
+                            inst_slice,
+                            other_slice,
+                            mod_def_core.allow_enum_mismatch,
+                        ),
+                        PortSliceOrWire::Wire(wire) => match net_first_slices.get(&wire.name) {
+                            Some(other_slice) => Self::check_enum_types(
+                                inst_slice,
+                                other_slice,
+                                mod_def_core.allow_enum_mismatch,
+                            ),
+                            None => {
+                                net_first_slices.insert(wire.name.clone(), inst_slice);
+                            }
+                        },
+                    }
+
                     let inst_slice_key = inst_slice.port.to_port_key();
 
                     match inst_slice.port.io() {
@@ -2368,6 +4407,98 @@ since the width of that port is {}. Check the slice indices for this instance po
     fn is_in_mod_def_core(slice: &PortSlice, mod_def_core: &Rc<RefCell<ModDefCore>>) -> bool {
         Rc::ptr_eq(&slice.port.get_mod_def_core(), mod_def_core)
     }
+
+    /// Panics with a message listing all connections to the port of `slice`,
+    /// for use when one of them is inconsistent with the port's direction.
+    fn panic_inconsistent_direction(slice: &PortSlice, problem: &str, core: &ModDefCore) -> ! {
+        let mut connections = Vec::new();
+        for Assignment { lhs, rhs, .. } in &core.assignments {
+            if lhs.is_same_port(slice) {
+                connections.push(format!(
+                    "  {} is driven by {}",
+                    lhs.debug_string(),
+                    rhs.debug_string()
+                ));
+            }
+            if rhs.is_same_port(slice) {
+                connections.push(format!(
+                    "  {} drives {}",
+                    rhs.debug_string(),
+                    lhs.debug_string()
+                ));
+            }
+        }
+        panic!(
+            "{} {}, since {} is {} {}. Connections to {}:\n{}",
+            slice.debug_string(),
+            problem,
+            slice.port.debug_string(),
+            match slice.port {
+                Port::ModDef { .. } => "a module definition",
+                Port::ModInst { .. } => "a module instance",
+            },
+            match slice.port.io() {
+                IO::Input(_) => "input",
+                IO::Output(_) => "output",
+                IO::InOut(_) => "inout",
+            },
+            slice.port.debug_string(),
+            connections.join("\n")
+        );
+    }
+
+    fn check_enum_types(a: &PortSlice, b: &PortSlice, allow_enum_mismatch: bool) {
+        match (a.port.enum_type(), b.port.enum_type()) {
+            (Some(a_type), Some(b_type)) => {
+                if a_type != b_type {
+                    panic!(
+                        "Enum type mismatch in connection between {} (enum type {}) and {} (enum type {})",
+                        a.debug_string(),
+                        a_type,
+                        b.debug_string(),
+                        b_type
+                    );
+                }
+            }
+            (Some(enum_type), None) => {
+                Self::check_enum_mismatch_allowed(a, &enum_type, b, allow_enum_mismatch)
+            }
+            (None, Some(enum_type)) => {
+                Self::check_enum_mismatch_allowed(b, &enum_type, a, allow_enum_mismatch)
+            }
+            (None, None) => {}
+        }
+    }
+
+    /// Panics if connecting `enum_slice` to `other_slice`, which is not
+    /// enum-typed, is not allowed. Connections that pass an entire instance
+    /// port through to a module definition port of the same name, as created
+    /// by `wrap()` and `export()`, are always allowed.
+    fn check_enum_mismatch_allowed(
+        enum_slice: &PortSlice,
+        enum_type: &str,
+        other_slice: &PortSlice,
+        allow_enum_mismatch: bool,
+    ) {
+        let is_pass_through = |inst_slice: &PortSlice, mod_def_slice: &PortSlice| {
+            matches!(
+                (&inst_slice.port, &mod_def_slice.port),
+                (Port::ModInst { port_name, .. }, Port::ModDef { name, .. }) if port_name == name
+            ) && inst_slice.width() == inst_slice.port.io().width()
+                && mod_def_slice.width() == mod_def_slice.port.io().width()
+        };
+        if is_pass_through(enum_slice, other_slice) || is_pass_through(other_slice, enum_slice) {
+            return;
+        }
+        if !allow_enum_mismatch {
+            panic!(
+                "Connection between {} (enum type {}) and {} (not enum-typed) requires set_allow_enum_mismatch(true)",
+                enum_slice.debug_string(),
+                enum_type,
+                other_slice.debug_string()
+            );
+        }
+    }
 }
 
 impl Port {
@@ -2385,6 +4516,28 @@ impl Port {
         }
     }
 
+    /// Returns the name of the enum type associated with this port, if any.
+    fn enum_type(&self) -> Option<String> {
+        match self {
+            Port::ModDef { mod_def_core, name } => mod_def_core
+                .upgrade()
+                .unwrap()
+                .borrow()
+                .enum_ports
+                .get(name)
+                .cloned(),
+            Port::ModInst {
+                mod_def_core,
+                inst_name,
+                port_name,
+            } => mod_def_core.upgrade().unwrap().borrow().instances[inst_name]
+                .borrow()
+                .enum_ports
+                .get(port_name)
+                .cloned(),
+        }
+    }
+
     fn debug_string(&self) -> String {
         match self {
             Port::ModDef { name, mod_def_core } => {
@@ -2403,10 +4556,6 @@ impl Port {
         }
     }
 
-    fn debug_string_with_width(&self) -> String {
-        format!("{}[{}:{}]", self.debug_string(), self.io().width() - 1, 0)
-    }
-
     /// Connects this port to a net with a specific name.
     pub fn connect_to_net(&self, net: &str) {
         self.to_port_slice().connect_to_net(net);
@@ -2421,6 +4570,43 @@ impl Port {
         self.connect_generic(other, Some(pipeline));
     }
 
+    /// Connects this output port of a module definition directly back to
+    /// `input`, an input port of the same module definition, creating a
+    /// loopback (feedthrough) within the module definition. Unlike
+    /// `connect()`, which accepts either order and any pair of ports, this
+    /// panics unless both ports are on the same module definition, this port
+    /// is an output, and `input` is an input.
+    pub fn connect_to_self(&self, input: &Port) {
+        for port in [self, input] {
+            if let Port::ModInst { .. } = port {
+                panic!(
+                    "Cannot create loopback with {}: it is a port of a module instance",
+                    port.debug_string()
+                );
+            }
+        }
+        if !Rc::ptr_eq(&self.get_mod_def_core(), &input.get_mod_def_core()) {
+            panic!(
+                "Cannot create loopback between {} and {}: they are on different module definitions",
+                self.debug_string(),
+                input.debug_string()
+            );
+        }
+        if !matches!(self.io(), IO::Output(_)) {
+            panic!(
+                "Cannot create loopback from {}: it is not an output",
+                self.debug_string()
+            );
+        }
+        if !matches!(input.io(), IO::Input(_)) {
+            panic!(
+                "Cannot create loopback to {}: it is not an input",
+                input.debug_string()
+            );
+        }
+        self.connect(input);
+    }
+
     fn connect_generic<T: ConvertibleToPortSlice>(
         &self,
         other: &T,
@@ -2429,6 +4615,19 @@ impl Port {
         self.to_port_slice().connect_generic(other, pipeline);
     }
 
+    /// Merges connections involving this port that were made piece by piece
+    /// into wider connections where possible. See
+    /// `PortSlice::merge_connections()` for details.
+    pub fn merge_connections(&self) {
+        self.to_port_slice().merge_connections();
+    }
+
+    /// Connects bit `i` of this port to bit `perm[i]` of another port or port
+    /// slice. See `PortSlice::connect_permuted()` for details.
+    pub fn connect_permuted<T: ConvertibleToPortSlice>(&self, other: &T, perm: &[usize]) {
+        self.to_port_slice().connect_permuted(other, perm);
+    }
+
     /// Punches a feedthrough in the provided module definition for this port.
     pub fn feedthrough(
         &self,
@@ -2490,14 +4689,37 @@ impl Port {
         self.to_port_slice().unused();
     }
 
+    /// Records that this module definition port has the SystemVerilog enum
+    /// type `type_name` (e.g., `my_pkg::state_t`). When an instance of this
+    /// module definition is emitted, connections to this port are cast to the
+    /// enum type if it is an input, e.g. `.state(my_pkg::state_t'(net))`.
+    /// Panics if this is a port on a module instance.
+    pub fn set_enum_type(&self, type_name: impl AsRef<str>) {
+        match self {
+            Port::ModDef { mod_def_core, name } => {
+                mod_def_core
+                    .upgrade()
+                    .unwrap()
+                    .borrow_mut()
+                    .enum_ports
+                    .insert(name.clone(), type_name.as_ref().to_string());
+            }
+            Port::ModInst { .. } => panic!(
+                "Cannot set the enum type of {}: set_enum_type() can only be called on module definition ports",
+                self.debug_string()
+            ),
+        }
+    }
+
     /// Returns a slice of this port from `msb` down to `lsb`, inclusive.
     pub fn slice(&self, msb: usize, lsb: usize) -> PortSlice {
         if msb >= self.io().width() || lsb > msb {
             panic!(
-                "Invalid slice [{}:{}] of port {}",
+                "Invalid slice [{}:{}] of port {}, which has width {}",
                 msb,
                 lsb,
-                self.debug_string_with_width()
+                self.debug_string(),
+                self.io().width()
             );
         }
         PortSlice {
@@ -2548,6 +4770,68 @@ impl Port {
     pub fn export(&self) -> Port {
         self.to_port_slice().export()
     }
+
+    /// Returns the names of all ports connected to this port within the module
+    /// definition that contains it, without duplicates. Connections made via
+    /// `connect_to_net()` are followed to the other ports on the same net.
+    /// Port names are returned without instance names, so ports with the same
+    /// name on different instances are reported once.
+    pub fn get_connected_port_names(&self) -> Vec<String> {
+        let this = self.to_port_slice();
+        let core_rc = self.get_mod_def_core();
+        let core = core_rc.borrow();
+
+        let mut connected: Vec<PortSlice> = Vec::new();
+
+        for Assignment { lhs, rhs, .. } in &core.assignments {
+            if lhs.is_same_port(&this) {
+                connected.push(rhs.clone());
+            } else if rhs.is_same_port(&this) {
+                connected.push(lhs.clone());
+            }
+        }
+
+        let mut net_names = HashSet::new();
+        let inst_connections = core
+            .inst_connections
+            .values()
+            .flat_map(|port_connections| port_connections.values())
+            .flatten();
+        for inst_connection in inst_connections.clone() {
+            match &inst_connection.connected_to {
+                PortSliceOrWire::PortSlice(other) => {
+                    if inst_connection.inst_port_slice.is_same_port(&this) {
+                        connected.push(other.clone());
+                    } else if other.is_same_port(&this) {
+                        connected.push(inst_connection.inst_port_slice.clone());
+                    }
+                }
+                PortSliceOrWire::Wire(wire) => {
+                    if inst_connection.inst_port_slice.is_same_port(&this) {
+                        net_names.insert(wire.name.clone());
+                    }
+                }
+            }
+        }
+        for inst_connection in inst_connections {
+            if let PortSliceOrWire::Wire(wire) = &inst_connection.connected_to {
+                if net_names.contains(&wire.name)
+                    && !inst_connection.inst_port_slice.is_same_port(&this)
+                {
+                    connected.push(inst_connection.inst_port_slice.clone());
+                }
+            }
+        }
+
+        let mut result: Vec<String> = Vec::new();
+        for slice in connected {
+            let name = slice.port.get_port_name();
+            if !result.contains(&name) {
+                result.push(name);
+            }
+        }
+        result
+    }
 }
 
 impl PortSlice {
@@ -2630,6 +4914,53 @@ impl PortSlice {
         self.connect_generic(other, Some(pipeline));
     }
 
+    /// Connects bit `i` of this port slice to bit `perm[i]` of another port or
+    /// port slice, where bit indices are relative to the least significant bit
+    /// of each slice. For example, if `perm` is `&[1, 0]`, bit 0 of this slice
+    /// is connected to bit 1 of `other`, and vice versa. Each bit is connected
+    /// separately with the same checks as `connect()`. Panics if the length of
+    /// `perm` is not the width of this slice, or if `perm` contains an index
+    /// that is out of range for `other` or that appears more than once.
+    pub fn connect_permuted<T: ConvertibleToPortSlice>(&self, other: &T, perm: &[usize]) {
+        let other_as_slice = other.to_port_slice();
+
+        if perm.len() != self.width() {
+            panic!(
+                "Permutation for connecting {} to {} has length {}, but must have length {}",
+                self.debug_string(),
+                other_as_slice.debug_string(),
+                perm.len(),
+                self.width()
+            );
+        }
+
+        let mut used = HashSet::new();
+        for &index in perm {
+            if index >= other_as_slice.width() {
+                panic!(
+                    "Permutation for connecting {} to {} contains index {}, which is out of range for {}",
+                    self.debug_string(),
+                    other_as_slice.debug_string(),
+                    index,
+                    other_as_slice.debug_string()
+                );
+            }
+            if !used.insert(index) {
+                panic!(
+                    "Permutation for connecting {} to {} contains index {} more than once",
+                    self.debug_string(),
+                    other_as_slice.debug_string(),
+                    index
+                );
+            }
+        }
+
+        for (i, &index) in perm.iter().enumerate() {
+            self.slice_relative(i, 1)
+                .connect(&other_as_slice.slice_relative(index, 1));
+        }
+    }
+
     fn connect_generic<T: ConvertibleToPortSlice>(
         &self,
         other: &T,
@@ -2637,6 +4968,16 @@ impl PortSlice {
     ) {
         let other_as_slice = other.to_port_slice();
 
+        if self.port.to_port_key() == other_as_slice.port.to_port_key()
+            && self.msb == other_as_slice.msb
+            && self.lsb == other_as_slice.lsb
+        {
+            panic!(
+                "Cannot connect a port slice to itself: {}",
+                self.debug_string()
+            );
+        }
+
         let mod_def_core = self.get_mod_def_core();
 
         if let (IO::InOut(_), _) | (_, IO::InOut(_)) = (self.port.io(), other_as_slice.port.io()) {
@@ -2936,6 +5277,51 @@ impl PortSlice {
         mod_def_core.borrow_mut().unused.push((*self).clone());
     }
 
+    fn mask(&self) -> BigUint {
+        ((BigUint::from(1u32) << self.width()) - BigUint::from(1u32)) << self.lsb
+    }
+
+    /// Returns the name of this port slice relative to the module definition
+    /// containing it, e.g. `a[7:0]` or `inst.x[3:0]`.
+    fn local_name(&self) -> String {
+        match &self.port {
+            Port::ModDef { name, .. } => format!("{}[{}:{}]", name, self.msb, self.lsb),
+            Port::ModInst {
+                inst_name,
+                port_name,
+                ..
+            } => format!("{}.{}[{}:{}]", inst_name, port_name, self.msb, self.lsb),
+        }
+    }
+
+    /// Returns `true` if any bit of this port slice is connected, tied off, or
+    /// marked as unused within the given module definition.
+    fn has_connections(&self, core: &ModDefCore) -> bool {
+        core.assignments
+            .iter()
+            .any(|a| a.lhs.overlaps(self) || a.rhs.overlaps(self))
+            || core.tieoffs.iter().any(|(s, _)| s.overlaps(self))
+            || core.unused.iter().any(|s| s.overlaps(self))
+            || core
+                .inst_connections
+                .values()
+                .flat_map(|port_connections| port_connections.values())
+                .flatten()
+                .any(|c| {
+                    c.inst_port_slice.overlaps(self)
+                        || match &c.connected_to {
+                            PortSliceOrWire::PortSlice(s) => s.overlaps(self),
+                            PortSliceOrWire::Wire(_) => false,
+                        }
+                })
+    }
+
+    fn overlaps(&self, other: &PortSlice) -> bool {
+        self.port.to_port_key() == other.port.to_port_key()
+            && self.lsb <= other.msb
+            && other.lsb <= self.msb
+    }
+
     fn check_validity(&self) {
         if self.msb >= self.port.io().width() {
             panic!(
@@ -2972,11 +5358,17 @@ impl ModInst {
     /// Returns the port on this instance with the given name. Panics if no such
     /// port exists.
     pub fn get_port(&self, name: impl AsRef<str>) -> Port {
-        ModDef {
-            core: self.mod_def_core.upgrade().unwrap().borrow().instances[&self.name].clone(),
+        let mod_def = self.get_mod_def();
+        if !mod_def.has_port(name.as_ref()) {
+            panic!(
+                "Port {} does not exist on instance {} of module definition {}. Available ports: {}",
+                name.as_ref(),
+                self.debug_string(),
+                mod_def.get_name(),
+                mod_def.core.borrow().ports.keys().join(", ")
+            );
         }
-        .get_port(name)
-        .assign_to_inst(self)
+        mod_def.get_port(name).assign_to_inst(self)
     }
 
     /// Returns a slice of the port on this instance with the given name, from
@@ -3030,6 +5422,45 @@ impl ModInst {
         }
     }
 
+    /// Overrides a parameter of this instance with the given Verilog
+    /// expression, which may refer to parameters declared on the parent module
+    /// definition via `ModDef::add_parameter()` (e.g., `.WIDTH(WIDTH)`).
+    /// Panics if the instantiated module definition does not declare a
+    /// parameter with that name, unless it was imported from external Verilog
+    /// sources, in which case the parameter is not checked.
+    pub fn set_parameter(&self, name: impl AsRef<str>, value: impl AsRef<str>) {
+        let mod_def = self.get_mod_def();
+        if !mod_def.has_parameter(name.as_ref()) && mod_def.core.borrow().verilog_import.is_none() {
+            panic!(
+                "Parameter {} does not exist on module definition {} (instance {})",
+                name.as_ref(),
+                mod_def.get_name(),
+                self.debug_string()
+            );
+        }
+
+        self.mod_def_core
+            .upgrade()
+            .unwrap()
+            .borrow_mut()
+            .inst_parameters
+            .entry(self.name.clone())
+            .or_default()
+            .insert(name.as_ref().to_string(), value.as_ref().to_string());
+    }
+
+    /// Sets a comment to be emitted as `//` lines immediately before this
+    /// instance, e.g. to record where in a generator the instance was created.
+    /// The comment may span multiple lines.
+    pub fn set_comment(&self, comment: impl AsRef<str>) {
+        self.mod_def_core
+            .upgrade()
+            .unwrap()
+            .borrow_mut()
+            .inst_comments
+            .insert(self.name.clone(), comment.as_ref().to_string());
+    }
+
     /// Returns the ModDef that this is an instance of.
     pub fn get_mod_def(&self) -> ModDef {
         ModDef {
@@ -3068,6 +5499,20 @@ pub enum Intf {
     },
 }
 
+/// Summary of the directions of the ports in an interface, as returned by
+/// `Intf::directionality()`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IntfDirectionality {
+    /// All ports are inputs.
+    AllInput,
+    /// All ports are outputs.
+    AllOutput,
+    /// The interface has both inputs and outputs, but no inouts.
+    Mixed,
+    /// At least one port is an inout.
+    Bidirectional,
+}
+
 impl std::fmt::Debug for Intf {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let mod_def_core = self.get_mod_def_core();
@@ -3244,6 +5689,134 @@ impl Intf {
         }
     }
 
+    /// Connects the functions that this interface has in common with another
+    /// interface, skipping the rest. Returns a tuple of `(matched_count,
+    /// unmatched_count)`, where `matched_count` is the number of functions
+    /// that were connected, and `unmatched_count` is the number of functions
+    /// present on only one of the two interfaces.
+    pub fn connect_partial(&self, other: &Intf) -> (usize, usize) {
+        let self_ports = self.get_port_slices();
+        let other_ports = other.get_port_slices();
+
+        let mut matched_count = 0;
+        for (func_name, self_port) in &self_ports {
+            if let Some(other_port) = other_ports.get(func_name) {
+                self_port.connect(other_port);
+                matched_count += 1;
+            }
+        }
+
+        let unmatched_count =
+            (self_ports.len() - matched_count) + (other_ports.len() - matched_count);
+
+        (matched_count, unmatched_count)
+    }
+
+    /// Compares the functions of this interface with those of another
+    /// interface. Returns a tuple of three vectors of function names: functions
+    /// only present in this interface, functions only present in the other
+    /// interface, and functions present in both interfaces but with different
+    /// widths. If all three vectors are empty, the two interfaces can be
+    /// connected with `allow_mismatch` set to `false`.
+    pub fn diff(&self, other: &Intf) -> (Vec<String>, Vec<String>, Vec<String>) {
+        let self_ports = self.get_port_slices();
+        let other_ports = other.get_port_slices();
+
+        let mut only_in_self = Vec::new();
+        let mut width_mismatch = Vec::new();
+
+        for (func_name, self_port) in &self_ports {
+            match other_ports.get(func_name) {
+                Some(other_port) => {
+                    if self_port.width() != other_port.width() {
+                        width_mismatch.push(func_name.clone());
+                    }
+                }
+                None => only_in_self.push(func_name.clone()),
+            }
+        }
+
+        let only_in_other = other_ports
+            .keys()
+            .filter(|func_name| !self_ports.contains_key(*func_name))
+            .cloned()
+            .collect();
+
+        (only_in_self, only_in_other, width_mismatch)
+    }
+
+    /// Panics if this interface cannot be connected to `other` with
+    /// `allow_mismatch` set to `false`. The panic message lists every problem
+    /// found: functions present in only one of the two interfaces, functions
+    /// whose widths differ, and functions where neither side can drive the
+    /// other (e.g., two module instance inputs).
+    pub fn assert_compatible_with(&self, other: &Intf) {
+        let self_ports = self.get_port_slices();
+        let other_ports = other.get_port_slices();
+
+        let mut mismatches = Vec::new();
+
+        for (func_name, self_port) in &self_ports {
+            let Some(other_port) = other_ports.get(func_name) else {
+                mismatches.push(format!(
+                    "function '{}' is present in {} but not in {}",
+                    func_name,
+                    self.debug_string(),
+                    other.debug_string()
+                ));
+                continue;
+            };
+
+            if self_port.width() != other_port.width() {
+                mismatches.push(format!(
+                    "function '{}' has width {} in {} but width {} in {}",
+                    func_name,
+                    self_port.width(),
+                    self_port.debug_string(),
+                    other_port.width(),
+                    other_port.debug_string()
+                ));
+            }
+
+            let self_drives = ModDef::can_drive(self_port) && ModDef::can_be_driven(other_port);
+            let other_drives = ModDef::can_drive(other_port) && ModDef::can_be_driven(self_port);
+            if !self_drives && !other_drives {
+                mismatches.push(format!(
+                    "function '{}' has incompatible directions: {} is {:?} and {} is {:?}",
+                    func_name,
+                    self_port.debug_string(),
+                    self_port.port.io(),
+                    other_port.debug_string(),
+                    other_port.port.io()
+                ));
+            }
+        }
+
+        for func_name in other_ports.keys() {
+            if !self_ports.contains_key(func_name) {
+                mismatches.push(format!(
+                    "function '{}' is present in {} but not in {}",
+                    func_name,
+                    other.debug_string(),
+                    self.debug_string()
+                ));
+            }
+        }
+
+        if !mismatches.is_empty() {
+            panic!(
+                "Interfaces {} and {} are not compatible:\n{}",
+                self.debug_string(),
+                other.debug_string(),
+                mismatches
+                    .iter()
+                    .map(|mismatch| format!("  {}", mismatch))
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            );
+        }
+    }
+
     /// Signals matching regex `pattern_a` on one interface are connected to
     /// signals matching regex `pattern_b` on the other interface, and vice
     /// versa. For example, suppose that this interface is `{"data_tx":
@@ -3350,7 +5923,8 @@ impl Intf {
     /// "valid": "a_valid"}` and the prefix is "b_", the new interface will be
     /// `{"data": "b_data", "valid": "b_valid"}`. The `name` argument specifies
     /// the name of the new interface, which is used to retrieve the interface
-    /// with `get_intf`.
+    /// with `get_intf`. The new interface is returned, so it can be connected
+    /// or exported further, e.g. when building multiple levels of wrappers.
     pub fn export_with_prefix(&self, name: impl AsRef<str>, prefix: impl AsRef<str>) -> Intf {
         let mut mapping = IndexMap::new();
         for (func_name, port_slice) in self.get_port_slices() {
@@ -3400,6 +5974,45 @@ impl Intf {
         .def_intf(self.get_intf_name(), mapping)
     }
 
+    /// Exports an interface from a module instance to the parent module
+    /// definition, returning a new interface with the same name. Unlike
+    /// `export()`, each new port is named after the function of the signal in
+    /// the interface, rather than the name of the port on the instance. For
+    /// example, if an instance has an interface "a" with function "data"
+    /// mapped to port "a_data", this creates a port "data" on the parent
+    /// module definition. Panics if any of the new ports would collide with an
+    /// existing port on the parent module definition.
+    pub fn export_preserving_names(&self) -> Intf {
+        if matches!(self, Intf::ModDef { .. }) {
+            panic!("Cannot export_preserving_names() {}; must use export_with_prefix() or export_with_name_underscore() instead.", self.debug_string());
+        }
+
+        let mod_def = ModDef {
+            core: self.get_mod_def_core(),
+        };
+        let port_slices = self.get_port_slices();
+
+        let collisions: Vec<&String> = port_slices
+            .keys()
+            .filter(|func_name| mod_def.has_port(func_name))
+            .collect();
+        if !collisions.is_empty() {
+            panic!(
+                "Cannot export_preserving_names() {}: port(s) {} already exist on module definition {}",
+                self.debug_string(),
+                collisions.iter().join(", "),
+                mod_def.get_name()
+            );
+        }
+
+        let mut mapping = IndexMap::new();
+        for (func_name, port_slice) in port_slices {
+            port_slice.export_as(&func_name);
+            mapping.insert(func_name.clone(), (func_name, port_slice.width() - 1, 0));
+        }
+        mod_def.def_intf(self.get_intf_name(), mapping)
+    }
+
     pub fn flip_to(&self, mod_def: &ModDef) -> Intf {
         let mut mapping = IndexMap::new();
         for (func_name, port_slice) in self.get_port_slices() {
@@ -3409,6 +6022,56 @@ impl Intf {
         mod_def.def_intf(self.get_intf_name(), mapping)
     }
 
+    /// Returns a new interface with the same functional names as this one, in
+    /// which inputs become outputs and vice versa, leaving inout ports
+    /// unchanged. This is useful when an interface was defined from the
+    /// opposite perspective (e.g., manager vs. subordinate). The new interface
+    /// is defined on a stub of this interface's module definition, named
+    /// `<module>_mirrored`, in which the ports of the interface are flipped;
+    /// this interface and its module definition are left unchanged. Since
+    /// interfaces only hold a weak reference to their module definition, the
+    /// stub is returned along with the interface. Panics if this is an
+    /// interface on a module instance, or if any port in the interface is only
+    /// partially included in it.
+    pub fn mirror(&self) -> (ModDef, Intf) {
+        let (mod_def_core, name) = match self {
+            Intf::ModDef { mod_def_core, name } => (mod_def_core.upgrade().unwrap(), name),
+            Intf::ModInst { .. } => panic!(
+                "Cannot mirror interface {}: only interfaces on module definitions can be mirrored",
+                self.debug_string()
+            ),
+        };
+
+        let port_slices = self.get_port_slices();
+        for port_slice in port_slices.values() {
+            if port_slice.width() != port_slice.port.io().width() {
+                panic!(
+                    "Cannot mirror interface {}: {} does not include the entire port",
+                    self.debug_string(),
+                    port_slice.debug_string()
+                );
+            }
+        }
+
+        let mod_def = ModDef { core: mod_def_core };
+        let mirrored = mod_def.stub(format!("{}_mirrored", mod_def.get_name()));
+
+        {
+            let mut core = mirrored.core.borrow_mut();
+            for port_slice in port_slices.values() {
+                let io = core.ports.get_mut(port_slice.port.name()).unwrap();
+                *io = io.flip();
+            }
+        }
+
+        let intf = Intf::ModDef {
+            name: name.clone(),
+            mod_def_core: Rc::downgrade(&mirrored.core),
+        };
+
+        (mirrored, intf)
+    }
+
     pub fn copy_to(&self, mod_def: &ModDef) -> Intf {
         let mut mapping = IndexMap::new();
         for (func_name, port_slice) in self.get_port_slices() {
@@ -3679,6 +6342,72 @@ impl Intf {
 
         result
     }
+
+    /// Returns whether the ports of this interface are all inputs, all
+    /// outputs, a mix of the two, or include inouts. Directions are as
+    /// declared on the module definition, so an interface on an instance has
+    /// the same directionality as the corresponding interface on its module
+    /// definition. For example, an `AllOutput` interface can be connected to
+    /// an `AllInput` interface with `connect()`, while `crossover()` is
+    /// typically used for `Mixed` interfaces.
+    pub fn directionality(&self) -> IntfDirectionality {
+        let ios: Vec<IO> = self
+            .get_port_slices()
+            .values()
+            .map(|port_slice| port_slice.port.io())
+            .collect();
+        if ios.iter().any(|io| matches!(io, IO::InOut(_))) {
+            IntfDirectionality::Bidirectional
+        } else if ios.iter().all(|io| matches!(io, IO::Input(_))) {
+            IntfDirectionality::AllInput
+        } else if ios.iter().all(|io| matches!(io, IO::Output(_))) {
+            IntfDirectionality::AllOutput
+        } else {
+            IntfDirectionality::Mixed
+        }
+    }
+
+    /// Splits this interface into two interfaces: one with the functions of
+    /// this interface that are inputs of the module definition, and one with
+    /// the functions that are outputs. Functions mapped to inout ports are
+    /// not included in either. The new interfaces are named by appending
+    /// "_inputs" and "_outputs" to the name of this interface; these names can
+    /// be used to retrieve them with `get_intf`.
+    pub fn split_by_direction(&self) -> (Intf, Intf) {
+        let Intf::ModDef { name, .. } = self else {
+            panic!(
+                "Error splitting {}: splitting ModInst interfaces is not supported.",
+                self.debug_string()
+            );
+        };
+
+        let mut inputs = IndexMap::new();
+        let mut outputs = IndexMap::new();
+        for (func_name, port_slice) in self.get_port_slices() {
+            let mapping = (
+                port_slice.port.get_port_name(),
+                port_slice.msb,
+                port_slice.lsb,
+            );
+            match port_slice.port.io() {
+                IO::Input(_) => {
+                    inputs.insert(func_name, mapping);
+                }
+                IO::Output(_) => {
+                    outputs.insert(func_name, mapping);
+                }
+                IO::InOut(_) => {}
+            }
+        }
+
+        let mod_def = ModDef {
+            core: self.get_mod_def_core(),
+        };
+        (
+            mod_def.def_intf(format!("{}_inputs", name), inputs),
+            mod_def.def_intf(format!("{}_outputs", name), outputs),
+        )
+    }
 }
 
 pub struct Funnel {
@@ -3686,8 +6415,15 @@ pub struct Funnel {
     a_out: PortSlice,
     b_in: PortSlice,
     b_out: PortSlice,
-    a_in_offset: usize,
-    a_out_offset: usize,
+    a_in_used: DrivenPortBits,
+    a_out_used: DrivenPortBits,
+    b_in_used: DrivenPortBits,
+    b_out_used: DrivenPortBits,
+    // First free bit of each channel, as long as bits have been used
+    // contiguously from bit 0. Set to None once connect_with_offset() places
+    // bits elsewhere, after which connect() searches for free bits instead.
+    a_in_offset: Option<usize>,
+    a_out_offset: Option<usize>,
 }
 
 impl Funnel {
@@ -3761,12 +6497,39 @@ impl Funnel {
         );
 
         Self {
+            a_in_used: DrivenPortBits::new(a_in.width()),
+            a_out_used: DrivenPortBits::new(a_out.width()),
+            b_in_used: DrivenPortBits::new(b_in.width()),
+            b_out_used: DrivenPortBits::new(b_out.width()),
+            a_in_offset: Some(0),
+            a_out_offset: Some(0),
             a_in,
             a_out,
             b_in,
             b_out,
-            a_in_offset: 0,
-            a_out_offset: 0,
+        }
+    }
+
+    /// Creates a funnel between two interfaces, each of which must consist of
+    /// exactly two signals: one input and one output. The input and output of
+    /// each side are determined from the directions of the interface signals,
+    /// and are validated in the same way as `Funnel::new()`.
+    pub fn from_intfs(a: &Intf, b: &Intf) -> Self {
+        let a_slices = Self::intf_pair(a, "A");
+        let b_slices = Self::intf_pair(b, "B");
+        Self::new(a_slices, b_slices)
+    }
+
+    fn intf_pair(intf: &Intf, side: &str) -> (PortSlice, PortSlice) {
+        let port_slices: Vec<PortSlice> = intf.get_port_slices().into_values().collect();
+        match <[PortSlice; 2]>::try_from(port_slices) {
+            Ok([first, second]) => (first, second),
+            Err(port_slices) => panic!(
+                "Funnel error: Side {} interface {} must have exactly two signals, but has {}",
+                side,
+                intf.debug_string(),
+                port_slices.len()
+            ),
         }
     }
 
@@ -3774,6 +6537,87 @@ impl Funnel {
         let a = a.to_port_slice();
         let b = b.to_port_slice();
 
+        let (next_offset, a_used, b_used) = if Self::check_connection(&a, &b) {
+            (self.a_in_offset, &self.a_in_used, &self.b_out_used)
+        } else {
+            (self.a_out_offset, &self.a_out_used, &self.b_in_used)
+        };
+
+        // Use the next free offset if bits have been used contiguously so far,
+        // and otherwise the lowest offset where the bits are free on both
+        // sides.
+        let width = a.width();
+        let offset = next_offset.unwrap_or_else(|| {
+            (0..a_used.width)
+                .take_while(|&offset| offset + width <= a_used.width)
+                .find(|&offset| {
+                    (offset..offset + width).all(|i| !a_used.is_driven(i) && !b_used.is_driven(i))
+                })
+                .unwrap_or_else(|| panic!("Funnel out of capacity."))
+        });
+
+        self.connect_with_offset(&a, &b, offset, offset);
+    }
+
+    /// Connects `a` and `b` through the funnel, where `a` uses the funnel bits
+    /// on side A starting at `a_offset`, and `b` uses the funnel bits on side
+    /// B starting at `b_offset`. This is useful when a narrow signal must be
+    /// placed in a specific bit range of the funnel. Panics if any of the
+    /// requested bits are out of range or already in use.
+    pub fn connect_with_offset(
+        &mut self,
+        a: &impl ConvertibleToPortSlice,
+        b: &impl ConvertibleToPortSlice,
+        a_offset: usize,
+        b_offset: usize,
+    ) {
+        let a = a.to_port_slice();
+        let b = b.to_port_slice();
+
+        let (a_side, a_used, b_side, b_used, next_offset) = if Self::check_connection(&a, &b) {
+            (
+                &self.a_in,
+                &mut self.a_in_used,
+                &self.b_out,
+                &mut self.b_out_used,
+                &mut self.a_in_offset,
+            )
+        } else {
+            (
+                &self.a_out,
+                &mut self.a_out_used,
+                &self.b_in,
+                &mut self.b_in_used,
+                &mut self.a_out_offset,
+            )
+        };
+
+        assert!(
+            a_offset + a.width() <= a_side.width() && b_offset + b.width() <= b_side.width(),
+            "Funnel out of capacity."
+        );
+
+        let a_slice = a_side.slice_relative(a_offset, a.width());
+        let b_slice = b_side.slice_relative(b_offset, b.width());
+
+        for (used, slice, offset) in [(a_used, &a_slice, a_offset), (b_used, &b_slice, b_offset)] {
+            if used.driven(offset + slice.width() - 1, offset).is_err() {
+                panic!("Funnel error: {} is already in use.", slice.debug_string());
+            }
+        }
+
+        *next_offset = match *next_offset {
+            Some(offset) if offset == a_offset && offset == b_offset => Some(offset + a.width()),
+            _ => None,
+        };
+
+        a_slice.connect(&a);
+        b_slice.connect(&b);
+    }
+
+    /// Checks that `a` and `b` can be connected through the funnel, returning
+    /// `true` if `a` is the driver and `false` if `b` is the driver.
+    fn check_connection(a: &PortSlice, b: &PortSlice) -> bool {
         assert!(
             a.width() == b.width(),
             "Funnel error: a and b must have the same width ({}, {})",
@@ -3781,44 +6625,19 @@ impl Funnel {
             b.debug_string()
         );
 
-        if a.port.is_driver() {
-            if b.port.is_driver() {
-                panic!(
-                    "Funnel error: Cannot connect two outputs together ({}, {})",
-                    a.debug_string(),
-                    b.debug_string()
-                );
-            } else {
-                assert!(
-                    self.a_in_offset + a.width() <= self.a_in.width(),
-                    "Funnel out of capacity."
-                );
-                self.a_in
-                    .slice_relative(self.a_in_offset, a.width())
-                    .connect(&a);
-                self.b_out
-                    .slice_relative(self.a_in_offset, b.width())
-                    .connect(&b);
-                self.a_in_offset += a.width();
-            }
-        } else if b.port.is_driver() {
-            assert!(
-                self.a_out_offset + a.width() <= self.a_out.width(),
-                "Funnel out of capacity."
-            );
-            self.a_out
-                .slice_relative(self.a_out_offset, a.width())
-                .connect(&a);
-            self.b_in
-                .slice_relative(self.a_out_offset, b.width())
-                .connect(&b);
-            self.a_out_offset += a.width();
-        } else {
-            panic!(
+        match (a.port.is_driver(), b.port.is_driver()) {
+            (true, false) => true,
+            (false, true) => false,
+            (true, true) => panic!(
+                "Funnel error: Cannot connect two outputs together ({}, {})",
+                a.debug_string(),
+                b.debug_string()
+            ),
+            (false, false) => panic!(
                 "Funnel error: Cannot connect two inputs together ({}, {})",
                 a.debug_string(),
                 b.debug_string()
-            );
+            ),
         }
     }
 
@@ -3902,26 +6721,88 @@ impl Funnel {
         }
     }
 
+    /// Connects the remaining unused bits of the A-to-B channel to the given
+    /// slices instead of tying them off: `in_slice` drives the unused bits of
+    /// the side A input, and `out_slice` is driven by the unused bits of the
+    /// side B output. Unused bits are assigned in ascending bit order. The
+    /// width of each slice must match the number of unused bits on the
+    /// corresponding side of the funnel.
+    pub fn connect_a2b_remaining_to(
+        &mut self,
+        in_slice: &impl ConvertibleToPortSlice,
+        out_slice: &impl ConvertibleToPortSlice,
+    ) {
+        Self::connect_remaining(
+            (&self.a_in, &mut self.a_in_used),
+            (&self.b_out, &mut self.b_out_used),
+            &in_slice.to_port_slice(),
+            &out_slice.to_port_slice(),
+        );
+    }
+
+    /// Same as `connect_a2b_remaining_to()`, but for the B-to-A channel:
+    /// `in_slice` drives the unused bits of the side B input, and `out_slice`
+    /// is driven by the unused bits of the side A output.
+    pub fn connect_b2a_remaining_to(
+        &mut self,
+        in_slice: &impl ConvertibleToPortSlice,
+        out_slice: &impl ConvertibleToPortSlice,
+    ) {
+        Self::connect_remaining(
+            (&self.b_in, &mut self.b_in_used),
+            (&self.a_out, &mut self.a_out_used),
+            &in_slice.to_port_slice(),
+            &out_slice.to_port_slice(),
+        );
+    }
+
+    fn connect_remaining(
+        channel_in: (&PortSlice, &mut DrivenPortBits),
+        channel_out: (&PortSlice, &mut DrivenPortBits),
+        in_slice: &PortSlice,
+        out_slice: &PortSlice,
+    ) {
+        for ((channel, used), slice) in [(channel_in, in_slice), (channel_out, out_slice)] {
+            let ranges = used.undriven_ranges();
+            let remaining: usize = ranges.iter().map(|(msb, lsb)| msb - lsb + 1).sum();
+            assert!(
+                slice.width() == remaining,
+                "Funnel error: {} has width {}, but {} has {} unused bits",
+                slice.debug_string(),
+                slice.width(),
+                channel.debug_string(),
+                remaining
+            );
+
+            let mut offset = 0;
+            for (msb, lsb) in ranges {
+                let width = msb - lsb + 1;
+                channel
+                    .slice_relative(lsb, width)
+                    .connect(&slice.slice_relative(offset, width));
+                offset += width;
+            }
+            used.drive_all();
+        }
+    }
+
     pub fn done(&mut self) {
-        if self.a_in_offset != self.a_in.width() {
-            self.a_in
-                .slice_relative(self.a_in_offset, self.a_in.width() - self.a_in_offset)
-                .tieoff(0);
-            self.b_out
-                .slice_relative(self.a_in_offset, self.b_out.width() - self.a_in_offset)
-                .unused();
-            self.a_in_offset = self.a_in.width();
-        }
-
-        if self.a_out_offset != self.a_out.width() {
-            self.a_out
-                .slice_relative(self.a_out_offset, self.a_out.width() - self.a_out_offset)
-                .unused();
-            self.b_in
-                .slice_relative(self.a_out_offset, self.b_in.width() - self.a_out_offset)
-                .tieoff(0);
-            self.a_out_offset = self.a_out.width();
+        for (msb, lsb) in self.a_in_used.undriven_ranges() {
+            self.a_in.slice_relative(lsb, msb - lsb + 1).tieoff(0);
+        }
+        self.a_in_used.drive_all();
+        for (msb, lsb) in self.b_out_used.undriven_ranges() {
+            self.b_out.slice_relative(lsb, msb - lsb + 1).unused();
+        }
+        self.b_out_used.drive_all();
+        for (msb, lsb) in self.a_out_used.undriven_ranges() {
+            self.a_out.slice_relative(lsb, msb - lsb + 1).unused();
+        }
+        self.a_out_used.drive_all();
+        for (msb, lsb) in self.b_in_used.undriven_ranges() {
+            self.b_in.slice_relative(lsb, msb - lsb + 1).tieoff(0);
         }
+        self.b_in_used.drive_all();
     }
 }
 
@@ -0,0 +1,572 @@
+// SPDX-License-Identifier: Apache-2.0
+
+// TODO: Replace with VAST API calls once parameters are supported.
+
+use indexmap::IndexMap;
+use num_bigint::BigUint;
+use regex::Regex;
+
+#[derive(Default)]
+pub struct ParameterInfo {
+    /// Map from module definition name to its parameter declarations, e.g.
+    /// `parameter int WIDTH = 8`.
+    pub declarations: IndexMap<String, Vec<String>>,
+    /// Map from module definition name to instance name to parameter
+    /// overrides, where each override maps a parameter name to an expression.
+    pub overrides: IndexMap<String, IndexMap<String, IndexMap<String, String>>>,
+    /// Map from module definition name to port name to the width of that port
+    /// and the expression that should be used for its width instead.
+    pub width_exprs: IndexMap<String, IndexMap<String, (usize, String)>>,
+}
+
+pub fn add_parameters(text: String, info: &ParameterInfo) -> String {
+    let mut lines: Vec<String> = Vec::new();
+
+    let width_replacements: IndexMap<&String, Vec<WidthReplacement>> = info
+        .width_exprs
+        .iter()
+        .map(|(def_name, width_exprs)| (def_name, width_replacements(width_exprs)))
+        .collect();
+
+    let mut current_mod_def_name: Option<String> = None;
+    let mut in_header = false;
+
+    for line in text.split('\n') {
+        let line = match current_mod_def_name
+            .as_ref()
+            .and_then(|def_name| width_replacements.get(def_name))
+        {
+            Some(replacements) => replace_widths(line, replacements, in_header),
+            None => line.to_string(),
+        };
+        let line = line.as_str();
+        let trimmed_line = line.trim();
+        if in_header && trimmed_line == ");" {
+            in_header = false;
+        }
+        if trimmed_line.starts_with("endmodule") {
+            current_mod_def_name = None;
+        } else if let Some(rest) = trimmed_line.strip_prefix("module ") {
+            let def_name = rest.split(['(', ';']).next().unwrap().trim().to_string();
+            current_mod_def_name = Some(def_name.clone());
+            in_header = !rest[def_name.len()..].starts_with(';');
+            if let Some(declarations) = info.declarations.get(&def_name) {
+                let suffix = if rest[def_name.len()..].starts_with(';') {
+                    ");"
+                } else {
+                    ") ("
+                };
+                lines.push(format!("module {} #(", def_name));
+                lines.push(
+                    declarations
+                        .iter()
+                        .map(|declaration| format!("  {}", declaration))
+                        .collect::<Vec<_>>()
+                        .join(",\n"),
+                );
+                lines.push(suffix.to_string());
+                continue;
+            }
+        } else if let Some(ref def_name) = current_mod_def_name {
+            if let Some(map_of_insts) = info.overrides.get(def_name) {
+                let tokens: Vec<&str> = trimmed_line.split_whitespace().collect();
+                if let [module_name, inst_name, "("] = tokens.as_slice() {
+                    if let Some(overrides) = map_of_insts.get(*inst_name) {
+                        let indent = &line[..line.len() - line.trim_start().len()];
+                        lines.push(format!("{}{} #(", indent, module_name));
+                        lines.push(
+                            overrides
+                                .iter()
+                                .map(|(name, value)| format!("{}  .{}({})", indent, name, value))
+                                .collect::<Vec<_>>()
+                                .join(",\n"),
+                        );
+                        lines.push(format!("{}) {} (", indent, inst_name));
+                        continue;
+                    }
+                }
+            }
+        }
+        lines.push(line.to_string());
+    }
+
+    lines.join("\n")
+}
+
+/// Regular expressions used to replace the width of a port with an
+/// expression, compiled once per module definition.
+struct WidthReplacement {
+    expr: String,
+    /// Port declaration, e.g. "  input wire [7:0] data,"
+    declaration: Regex,
+    /// Full-width reference to the port, e.g. "data[7:0]"
+    reference: Regex,
+    /// Replacement for a full-width reference, e.g. "data[WIDTH-1:0]"
+    replacement: String,
+    /// Tieoff of the full port, after replacing the reference, e.g.
+    /// "assign data[WIDTH-1:0] = 8'h00;"
+    tieoff: Regex,
+}
+
+fn width_replacements(width_exprs: &IndexMap<String, (usize, String)>) -> Vec<WidthReplacement> {
+    width_exprs
+        .iter()
+        .map(|(port_name, (width, expr))| {
+            let replacement = format!("{}[{}-1:0]", port_name, expr);
+            WidthReplacement {
+                expr: expr.clone(),
+                declaration: Regex::new(&format!(
+                    r"^(\s*(?:input|output|inout) wire )(?:\[\d+:0\] )?({})(,?)$",
+                    regex::escape(port_name)
+                ))
+                .unwrap(),
+                reference: Regex::new(&format!(
+                    r"\b{}\[{}:0\]",
+                    regex::escape(port_name),
+                    width - 1
+                ))
+                .unwrap(),
+                tieoff: Regex::new(&format!(
+                    r"^(\s*assign {} = )(\d+'h[0-9a-fA-F_]+);$",
+                    regex::escape(&replacement)
+                ))
+                .unwrap(),
+                replacement,
+            }
+        })
+        .collect()
+}
+
+fn replace_widths(line: &str, replacements: &[WidthReplacement], in_header: bool) -> String {
+    let mut line = line.to_string();
+
+    for replacement in replacements {
+        if in_header {
+            // Port declaration, e.g. "  input wire [7:0] data," becomes
+            // "  input wire [WIDTH-1:0] data,"
+            line = replacement
+                .declaration
+                .replace(
+                    &line,
+                    format!("${{1}}[{}-1:0] ${{2}}${{3}}", replacement.expr),
+                )
+                .to_string();
+        } else {
+            // Full-width references to the port, e.g. "data[7:0]"
+            line = replacement
+                .reference
+                .replace_all(&line, replacement.replacement.as_str())
+                .to_string();
+
+            // Tieoffs of the full port, e.g. "assign data[WIDTH-1:0] = 8'h00;"
+            // becomes "assign data[WIDTH-1:0] = (WIDTH)'(8'h00);"
+            line = replacement
+                .tieoff
+                .replace(&line, format!("${{1}}({})'(${{2}});", replacement.expr))
+                .to_string();
+        }
+    }
+
+    line
+}
+
+/// A parameter declaration parsed from Verilog source code.
+#[derive(Debug, PartialEq, Eq)]
+pub struct ParsedParameter {
+    pub name: String,
+    /// Type of the parameter, e.g. `int` or `logic [7:0]`; empty if no type
+    /// was given.
+    pub ty: String,
+    /// Default value expression of the parameter.
+    pub default: String,
+}
+
+/// Parses the `parameter` declarations of module `module_name` in `text`, both
+/// in the parameter port list and in the module body. `localparam`
+/// declarations, type parameters, and parameters without a default value are
+/// skipped. Returns an empty list if the module is not found.
+pub fn parse_parameters(text: &str, module_name: &str) -> Vec<ParsedParameter> {
+    let text = strip_comments(text);
+
+    let module_start = Regex::new(&format!(r"\bmodule\s+{}\b", regex::escape(module_name)))
+        .unwrap()
+        .find(&text);
+    let Some(module_start) = module_start else {
+        return Vec::new();
+    };
+    parse_module_parameters(&text[module_start.end()..], &ParameterRegexes::new())
+}
+
+/// Parses the `parameter` declarations of every module in `text`, as in
+/// `parse_parameters()`, returning them by module name. The text is only
+/// scanned once, regardless of the number of modules.
+pub fn parse_all_parameters(text: &str) -> IndexMap<String, Vec<ParsedParameter>> {
+    let text = strip_comments(text);
+    let regexes = ParameterRegexes::new();
+
+    let mut result = IndexMap::new();
+    for captures in Regex::new(r"\bmodule\s+(\w+)")
+        .unwrap()
+        .captures_iter(&text)
+    {
+        let module_text = &text[captures.get(0).unwrap().end()..];
+        result
+            .entry(captures[1].to_string())
+            .or_insert_with(|| parse_module_parameters(module_text, &regexes));
+    }
+    result
+}
+
+fn strip_comments(text: &str) -> std::borrow::Cow<'_, str> {
+    Regex::new(r"(?s)//[^\n]*|/\*.*?\*/")
+        .unwrap()
+        .replace_all(text, " ")
+}
+
+/// Regular expressions used to parse the parameters of a module, compiled
+/// once per call to `parse_parameters()` or `parse_all_parameters()`.
+struct ParameterRegexes {
+    /// End of the module
+    end: Regex,
+    /// Start of the parameter port list, e.g. "#("
+    list_open: Regex,
+    /// Parameter declaration in the module body, e.g. "parameter A = 1;"
+    declaration: Regex,
+}
+
+impl ParameterRegexes {
+    fn new() -> Self {
+        ParameterRegexes {
+            end: Regex::new(r"\bendmodule\b").unwrap(),
+            list_open: Regex::new(r"^\s*(?:import\s+[^;]*;\s*)*#\s*\(").unwrap(),
+            declaration: Regex::new(r"\bparameter\b([^;]*);").unwrap(),
+        }
+    }
+}
+
+/// Parses the `parameter` declarations of a module, where `module_text`
+/// starts right after the module name.
+fn parse_module_parameters(module_text: &str, regexes: &ParameterRegexes) -> Vec<ParsedParameter> {
+    let module_text = match regexes.end.find(module_text) {
+        Some(end) => &module_text[..end.start()],
+        None => module_text,
+    };
+
+    let mut result = Vec::new();
+
+    // Parameter port list, e.g. "#(parameter int A = 1, B = 2)"
+    let mut body_start = 0;
+    if let Some(list_open) = regexes.list_open.find(module_text) {
+        let list_start = list_open.end();
+        let list_end = list_start + find_closing_paren(&module_text[list_start..]);
+        parse_declaration_list(&module_text[list_start..list_end], true, &mut result);
+        body_start = list_end;
+    }
+
+    // Parameter declarations in the module body, e.g. "parameter A = 1;"
+    for captures in regexes
+        .declaration
+        .captures_iter(&module_text[body_start..])
+    {
+        parse_declaration_list(&captures[1], false, &mut result);
+    }
+
+    result
+}
+
+/// Parses a comma-separated list of parameter declarations, appending them to
+/// `result`. Items without a type inherit the type of the previous item.
+fn parse_declaration_list(text: &str, is_port_list: bool, result: &mut Vec<ParsedParameter>) {
+    let mut ty = String::new();
+    let mut is_local = false;
+    for item in split_top_level(text, ',') {
+        let mut item = item.trim();
+        if is_port_list {
+            if let Some(rest) = item.strip_prefix("localparam") {
+                is_local = true;
+                ty.clear();
+                item = rest.trim_start();
+            } else if let Some(rest) = item.strip_prefix("parameter") {
+                is_local = false;
+                ty.clear();
+                item = rest.trim_start();
+            }
+        }
+
+        let Some((lhs, default)) = split_top_level(item, '=')
+            .split_first()
+            .map(|(lhs, rest)| (lhs.trim().to_string(), rest.join("=").trim().to_string()))
+        else {
+            continue;
+        };
+
+        let (item_ty, name) = match lhs.rfind(|c: char| c.is_whitespace() || c == ']') {
+            Some(idx) => (lhs[..=idx].trim(), lhs[idx + 1..].trim()),
+            None => ("", lhs.as_str()),
+        };
+        if !item_ty.is_empty() {
+            ty = item_ty.to_string();
+        }
+
+        if is_local || default.is_empty() || ty == "type" || name.is_empty() {
+            continue;
+        }
+
+        result.push(ParsedParameter {
+            name: name.to_string(),
+            ty: ty.clone(),
+            default,
+        });
+    }
+}
+
+/// Returns the index of the parenthesis that closes an already-open
+/// parenthesis at the start of `text`.
+pub fn find_closing_paren(text: &str) -> usize {
+    let mut depth = 0;
+    for (idx, c) in text.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' if depth == 0 => return idx,
+            ')' => depth -= 1,
+            _ => {}
+        }
+    }
+    text.len()
+}
+
+/// Splits `text` at occurrences of `separator` that are not nested within
+/// parentheses, brackets, or braces.
+pub fn split_top_level(text: &str, separator: char) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut depth = 0;
+    let mut start = 0;
+    for (idx, c) in text.char_indices() {
+        match c {
+            '(' | '[' | '{' => depth += 1,
+            ')' | ']' | '}' => depth -= 1,
+            c if c == separator && depth == 0 => {
+                parts.push(&text[start..idx]);
+                start = idx + c.len_utf8();
+            }
+            _ => {}
+        }
+    }
+    parts.push(&text[start..]);
+    parts
+}
+
+/// Returns the value of a Verilog integer literal such as `8`, `'d8`, or
+/// `8'h08`, or `None` if `literal` is not a non-negative integer literal.
+pub fn parse_integer_literal(literal: &str) -> Option<BigUint> {
+    let literal = literal.trim().replace('_', "");
+    let (radix, digits) = match literal.split_once('\'') {
+        Some((size, rest)) => {
+            if !size.chars().all(|c| c.is_ascii_digit()) {
+                return None;
+            }
+            let rest = rest.strip_prefix(['s', 'S']).unwrap_or(rest);
+            let mut chars = rest.chars();
+            let radix = match chars.next()?.to_ascii_lowercase() {
+                'b' => 2,
+                'o' => 8,
+                'd' => 10,
+                'h' => 16,
+                _ => return None,
+            };
+            (radix, chars.as_str().trim().to_string())
+        }
+        None => (10, literal),
+    };
+    BigUint::parse_bytes(digits.as_bytes(), radix)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_add_parameters() {
+        let mut info = ParameterInfo::default();
+        info.declarations.insert(
+            "ModA".to_string(),
+            vec![
+                "parameter int WIDTH = 8".to_string(),
+                "parameter int DEPTH = 2".to_string(),
+            ],
+        );
+        info.declarations
+            .insert("ModC".to_string(), vec!["parameter N = 1".to_string()]);
+        info.overrides.insert("ModA".to_string(), IndexMap::new());
+        info.overrides["ModA"].insert("instB".to_string(), IndexMap::new());
+        info.overrides["ModA"]["instB"].insert("W".to_string(), "WIDTH".to_string());
+        info.overrides["ModA"]["instB"].insert("D".to_string(), "DEPTH + 1".to_string());
+
+        let input_verilog = "
+module ModA(
+  input wire [7:0] portA
+);
+  ModB instB (
+    .portA(portA)
+  );
+  ModB instC (
+    .portA(portA)
+  );
+endmodule
+module ModC;
+
+endmodule
+"
+        .to_string();
+
+        let expected_output = "
+module ModA #(
+  parameter int WIDTH = 8,
+  parameter int DEPTH = 2
+) (
+  input wire [7:0] portA
+);
+  ModB #(
+    .W(WIDTH),
+    .D(DEPTH + 1)
+  ) instB (
+    .portA(portA)
+  );
+  ModB instC (
+    .portA(portA)
+  );
+endmodule
+module ModC #(
+  parameter N = 1
+);
+
+endmodule
+"
+        .to_string();
+
+        let result = add_parameters(input_verilog, &info);
+        assert_eq!(result, expected_output);
+    }
+
+    #[test]
+    fn test_width_exprs() {
+        let mut info = ParameterInfo::default();
+        info.declarations.insert(
+            "ModA".to_string(),
+            vec!["parameter int WIDTH = 8".to_string()],
+        );
+        info.width_exprs.insert("ModA".to_string(), IndexMap::new());
+        info.width_exprs["ModA"].insert("a".to_string(), (8, "WIDTH".to_string()));
+        info.width_exprs["ModA"].insert("b".to_string(), (8, "WIDTH".to_string()));
+        info.width_exprs["ModA"].insert("c".to_string(), (1, "WIDTH/8".to_string()));
+
+        let input_verilog = "
+module ModA(
+  input wire [7:0] a,
+  output wire [7:0] b,
+  output wire c
+);
+  wire [7:0] inst_a;
+  ModB inst (
+    .a(inst_a)
+  );
+  assign inst_a[7:0] = a[7:0];
+  assign b[7:0] = 8'h05;
+  assign c = 1'h0;
+endmodule
+module ModB(
+  input wire [7:0] a
+);
+
+endmodule
+"
+        .to_string();
+
+        let expected_output = "
+module ModA #(
+  parameter int WIDTH = 8
+) (
+  input wire [WIDTH-1:0] a,
+  output wire [WIDTH-1:0] b,
+  output wire [WIDTH/8-1:0] c
+);
+  wire [7:0] inst_a;
+  ModB inst (
+    .a(inst_a)
+  );
+  assign inst_a[7:0] = a[WIDTH-1:0];
+  assign b[WIDTH-1:0] = (WIDTH)'(8'h05);
+  assign c = 1'h0;
+endmodule
+module ModB(
+  input wire [7:0] a
+);
+
+endmodule
+"
+        .to_string();
+
+        let result = add_parameters(input_verilog, &info);
+        assert_eq!(result, expected_output);
+    }
+
+    #[test]
+    fn test_parse_parameters() {
+        let verilog = "
+module Other #(parameter X = 1) ();
+endmodule
+module ModA import pkg::*; #(
+  parameter int WIDTH = 8, // comment
+  DEPTH = WIDTH * 2,
+  parameter logic [3:0] MODE = 4'hA,
+  localparam int HIDDEN = 3,
+  parameter type T = logic
+) (
+  input [WIDTH-1:0] a
+);
+  parameter N = 5, M = (N + 1);
+  /* parameter COMMENTED = 1; */
+  localparam L = 2;
+endmodule
+";
+
+        let param = |name: &str, ty: &str, default: &str| ParsedParameter {
+            name: name.to_string(),
+            ty: ty.to_string(),
+            default: default.to_string(),
+        };
+
+        assert_eq!(
+            parse_parameters(verilog, "ModA"),
+            vec![
+                param("WIDTH", "int", "8"),
+                param("DEPTH", "int", "WIDTH * 2"),
+                param("MODE", "logic [3:0]", "4'hA"),
+                param("N", "", "5"),
+                param("M", "", "(N + 1)"),
+            ]
+        );
+        assert_eq!(
+            parse_parameters(verilog, "Other"),
+            vec![param("X", "", "1")]
+        );
+        assert_eq!(parse_parameters(verilog, "Missing"), vec![]);
+
+        let all = parse_all_parameters(verilog);
+        assert_eq!(all.keys().collect::<Vec<_>>(), vec!["Other", "ModA"]);
+        assert_eq!(all["Other"], parse_parameters(verilog, "Other"));
+        assert_eq!(all["ModA"], parse_parameters(verilog, "ModA"));
+    }
+
+    #[test]
+    fn test_parse_integer_literal() {
+        assert_eq!(parse_integer_literal("42"), Some(BigUint::from(42u32)));
+        assert_eq!(parse_integer_literal("1_000"), Some(BigUint::from(1000u32)));
+        assert_eq!(parse_integer_literal("8'hFF"), Some(BigUint::from(255u32)));
+        assert_eq!(parse_integer_literal("'b101"), Some(BigUint::from(5u32)));
+        assert_eq!(parse_integer_literal("32'sd7"), Some(BigUint::from(7u32)));
+        assert_eq!(parse_integer_literal("4'o17"), Some(BigUint::from(15u32)));
+        assert_eq!(parse_integer_literal("WIDTH * 2"), None);
+        assert_eq!(parse_integer_literal("-1"), None);
+    }
+}
@@ -9,6 +9,7 @@ pub struct PipelineDetails<'a> {
     pub clk: &'a Expr,
     pub width: usize,
     pub depth: usize,
+    pub omit_out_stages: bool,
     pub pipe_in: &'a Expr,
     pub pipe_out: &'a Expr,
 }
@@ -26,18 +27,24 @@ pub fn add_pipeline(params: PipelineDetails) {
         .make_literal(&num_stages_str, &xlsynth::ir_value::IrFormatPreference::Hex)
         .unwrap();
 
+    let mut port_names = vec!["clk", "in", "out"];
+    let mut port_exprs = vec![
+        Some(params.clk),
+        Some(params.pipe_in),
+        Some(params.pipe_out),
+    ];
+    if !params.omit_out_stages {
+        port_names.push("out_stages");
+        port_exprs.push(None);
+    }
+
     let instantiation = params.file.make_instantiation(
         "br_delay_nr",
         params.inst_name,
         &["Width", "NumStages"],
         &[&width_expr, &num_stages_expr],
-        &["clk", "in", "out", "out_stages"],
-        &[
-            Some(params.clk),
-            Some(params.pipe_in),
-            Some(params.pipe_out),
-            None,
-        ],
+        &port_names,
+        &port_exprs,
     );
     params.module.add_member_instantiation(instantiation);
 }
@@ -64,6 +71,7 @@ mod tests {
             clk: &clk_wire.to_expr(),
             width: 0xab,
             depth: 0xcd,
+            omit_out_stages: false,
             pipe_in: &in_wire.to_expr(),
             pipe_out: &out_wire.to_expr(),
         };
@@ -87,6 +95,50 @@ module test;
     .out_stages()
   );
 endmodule
+"
+        );
+    }
+
+    #[test]
+    fn test_pipeline_omit_out_stages() {
+        let mut file = VastFile::new(VastFileType::SystemVerilog);
+        let mut module = file.add_module("test");
+        let clk_data_type = file.make_bit_vector_type(1, false);
+        let pipe_data_type = file.make_bit_vector_type(8, false);
+        let clk_wire = module.add_wire("clk", &clk_data_type);
+        let in_wire = module.add_wire("pipe_in", &pipe_data_type);
+        let out_wire = module.add_wire("pipe_out", &pipe_data_type);
+
+        let params = PipelineDetails {
+            file: &mut file,
+            module: &mut module,
+            inst_name: "br_delay_nr_i",
+            clk: &clk_wire.to_expr(),
+            width: 8,
+            depth: 2,
+            omit_out_stages: true,
+            pipe_in: &in_wire.to_expr(),
+            pipe_out: &out_wire.to_expr(),
+        };
+
+        add_pipeline(params);
+
+        assert_eq!(
+            file.emit(),
+            "\
+module test;
+  wire clk;
+  wire [7:0] pipe_in;
+  wire [7:0] pipe_out;
+  br_delay_nr #(
+    .Width(32'h0000_0008),
+    .NumStages(32'h0000_0002)
+  ) br_delay_nr_i (
+    .clk(clk),
+    .in(pipe_in),
+    .out(pipe_out)
+  );
+endmodule
 "
         );
     }
@@ -0,0 +1,105 @@
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::inout::INOUT_MARKER;
+
+/// Reorders the port declarations in each module header so that inputs come
+/// first, followed by outputs and then inouts, with a comment before each
+/// group. Relative order within each group is preserved.
+pub fn group_ports_by_direction(text: String) -> String {
+    let mut lines: Vec<String> = Vec::new();
+
+    let mut in_header = false;
+    let mut inputs: Vec<String> = Vec::new();
+    let mut outputs: Vec<String> = Vec::new();
+    let mut inouts: Vec<String> = Vec::new();
+
+    for line in text.split('\n') {
+        let trimmed_line = line.trim();
+        if in_header {
+            if trimmed_line == ");" {
+                in_header = false;
+                let groups = [
+                    ("inputs", &mut inputs),
+                    ("outputs", &mut outputs),
+                    ("inouts", &mut inouts),
+                ];
+                let num_ports: usize = groups.iter().map(|(_, ports)| ports.len()).sum();
+                let mut count = 0;
+                for (group_name, ports) in groups {
+                    if ports.is_empty() {
+                        continue;
+                    }
+                    lines.push(format!("  // {}", group_name));
+                    for port in ports.drain(..) {
+                        count += 1;
+                        let separator = if count < num_ports { "," } else { "" };
+                        lines.push(format!("{}{}", port, separator));
+                    }
+                }
+                lines.push(line.to_string());
+            } else {
+                let port = line.trim_end_matches(',').to_string();
+                if port.contains(INOUT_MARKER) || trimmed_line.starts_with("inout") {
+                    inouts.push(port);
+                } else if trimmed_line.starts_with("input") {
+                    inputs.push(port);
+                } else {
+                    outputs.push(port);
+                }
+            }
+            continue;
+        }
+        if trimmed_line.starts_with("module ") && trimmed_line.ends_with('(') {
+            in_header = true;
+        }
+        lines.push(line.to_string());
+    }
+
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_group_ports_by_direction() {
+        let input_verilog = "
+module ModA(
+  output wire [7:0] a,
+  input wire b,
+  input wire c_INOUT_RENAME,
+  input wire [3:0] d,
+  output wire e
+);
+
+endmodule
+module ModB;
+
+endmodule
+"
+        .to_string();
+
+        let expected_output = "
+module ModA(
+  // inputs
+  input wire b,
+  input wire [3:0] d,
+  // outputs
+  output wire [7:0] a,
+  output wire e,
+  // inouts
+  input wire c_INOUT_RENAME
+);
+
+endmodule
+module ModB;
+
+endmodule
+"
+        .to_string();
+
+        let result = group_ports_by_direction(input_verilog);
+        assert_eq!(result, expected_output);
+    }
+}
@@ -0,0 +1,250 @@
+// SPDX-License-Identifier: Apache-2.0
+
+use indexmap::IndexMap;
+use regex::Regex;
+
+use crate::parameter::{find_closing_paren, parse_integer_literal, split_top_level};
+
+/// The signals and modports of a SystemVerilog interface definition.
+#[derive(Debug, PartialEq, Eq)]
+pub struct ParsedInterface {
+    pub name: String,
+    /// Widths of the `logic` signals of the interface, in declaration order.
+    pub signals: IndexMap<String, usize>,
+    /// For each modport, the direction ("input", "output", or "inout") of each
+    /// signal listed in it.
+    pub modports: IndexMap<String, IndexMap<String, String>>,
+}
+
+/// Parses the first `interface ... endinterface` block in `text`. Only `logic`
+/// signals declared in the interface header or body, with at most one packed
+/// dimension whose bounds are integer literals, and modports listing plain
+/// signals are supported; other statements in the body are ignored. Returns
+/// `None` if no interface is found.
+pub fn parse_interface(text: &str) -> Option<ParsedInterface> {
+    let text = Regex::new(r"(?s)//[^\n]*|/\*.*?\*/")
+        .unwrap()
+        .replace_all(text, " ");
+
+    let interface_start = Regex::new(r"\binterface\s+(\w+)")
+        .unwrap()
+        .captures(&text)?;
+    let name = interface_start[1].to_string();
+    let mut rest = &text[interface_start.get(0).unwrap().end()..];
+    if let Some(end) = Regex::new(r"\bendinterface\b").unwrap().find(rest) {
+        rest = &rest[..end.start()];
+    }
+
+    let mut signals = IndexMap::new();
+    let mut modports = IndexMap::new();
+
+    // Parameter port list, e.g. "#(parameter W = 8)", which is skipped.
+    if let Some(after) = rest.trim_start().strip_prefix('#') {
+        if let Some(after) = after.trim_start().strip_prefix('(') {
+            rest = &after[(find_closing_paren(after) + 1).min(after.len())..];
+        }
+    }
+
+    // Port list, e.g. "(input logic clk, input logic rst)"
+    if let Some(after) = rest.trim_start().strip_prefix('(') {
+        let list_end = find_closing_paren(after);
+        let mut width = None;
+        for item in split_top_level(&after[..list_end], ',') {
+            let item = item.trim();
+            let item = ["input", "output", "inout"]
+                .iter()
+                .find_map(|direction| strip_keyword(item, direction))
+                .unwrap_or(item);
+            match strip_keyword(item, "logic") {
+                Some(declaration) => {
+                    let (declaration_width, names) = parse_declaration(declaration);
+                    width = Some(declaration_width);
+                    for name in names {
+                        signals.insert(name, declaration_width);
+                    }
+                }
+                None => match width {
+                    Some(width) => {
+                        signals.insert(parse_name(item), width);
+                    }
+                    None => panic!("Unsupported interface port declaration: {}", item),
+                },
+            }
+        }
+        rest = &after[(list_end + 1).min(after.len())..];
+    }
+
+    for statement in rest.split(';') {
+        let statement = statement.trim();
+        if let Some(declaration) = strip_keyword(statement, "logic") {
+            let (width, names) = parse_declaration(declaration);
+            for name in names {
+                signals.insert(name, width);
+            }
+        } else if let Some(declaration) = strip_keyword(statement, "modport") {
+            for modport in split_top_level(declaration, ',') {
+                let (modport_name, ports) = parse_modport(modport);
+                modports.insert(modport_name, ports);
+            }
+        }
+    }
+
+    Some(ParsedInterface {
+        name,
+        signals,
+        modports,
+    })
+}
+
+/// Returns the rest of `text` if it starts with the keyword `keyword`.
+fn strip_keyword<'a>(text: &'a str, keyword: &str) -> Option<&'a str> {
+    let rest = text.strip_prefix(keyword)?;
+    if rest.starts_with(|c: char| c.is_alphanumeric() || c == '_') {
+        None
+    } else {
+        Some(rest.trim_start())
+    }
+}
+
+/// Parses the part of a `logic` declaration after the keyword, e.g.
+/// `[7:0] a, b`, returning the width and names of the declared signals.
+fn parse_declaration(declaration: &str) -> (usize, Vec<String>) {
+    let declaration = strip_keyword(declaration, "signed")
+        .or_else(|| strip_keyword(declaration, "unsigned"))
+        .unwrap_or(declaration);
+
+    let (width, names) = match declaration.strip_prefix('[') {
+        Some(after) => {
+            let range_end = after
+                .find(']')
+                .unwrap_or_else(|| panic!("Unterminated range in declaration: {}", declaration));
+            (parse_range(&after[..range_end]), &after[range_end + 1..])
+        }
+        None => (1, declaration),
+    };
+
+    let names = split_top_level(names, ',')
+        .into_iter()
+        .map(parse_name)
+        .collect();
+
+    (width, names)
+}
+
+/// Returns the width of a packed dimension such as `7:0`.
+fn parse_range(range: &str) -> usize {
+    let bound = |bound: &str| {
+        parse_integer_literal(bound)
+            .and_then(|value| usize::try_from(&value).ok())
+            .unwrap_or_else(|| panic!("Unsupported range in interface declaration: [{}]", range))
+    };
+    match range.split_once(':') {
+        Some((msb, lsb)) => bound(msb).abs_diff(bound(lsb)) + 1,
+        None => panic!("Unsupported range in interface declaration: [{}]", range),
+    }
+}
+
+/// Returns `name` if it is a plain identifier, and panics otherwise (e.g., for
+/// unpacked arrays or declarations with initial values).
+fn parse_name(name: &str) -> String {
+    let name = name.trim();
+    if name.is_empty() || !name.chars().all(|c| c.is_alphanumeric() || c == '_') {
+        panic!("Unsupported signal declaration in interface: {}", name);
+    }
+    name.to_string()
+}
+
+/// Parses a single modport, e.g. `host (output req, input rsp)`, returning its
+/// name and the direction of each signal listed in it.
+fn parse_modport(modport: &str) -> (String, IndexMap<String, String>) {
+    let (name, ports) = modport
+        .split_once('(')
+        .unwrap_or_else(|| panic!("Unsupported modport declaration: {}", modport.trim()));
+    let ports = &ports[..find_closing_paren(ports)];
+
+    let mut result = IndexMap::new();
+    let mut direction = None;
+    for item in split_top_level(ports, ',') {
+        let item = item.trim();
+        let signal = match ["input", "output", "inout"]
+            .iter()
+            .find_map(|d| strip_keyword(item, d).map(|rest| (d, rest)))
+        {
+            Some((d, rest)) => {
+                direction = Some(d.to_string());
+                rest
+            }
+            None => item,
+        };
+        let Some(direction) = &direction else {
+            panic!("Unsupported modport item: {}", item);
+        };
+        result.insert(parse_name(signal), direction.clone());
+    }
+
+    (name.trim().to_string(), result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_interface() {
+        let text = "
+// A simple bus
+interface bus_if (input logic clk);
+  logic [7:0] req, rsp;
+  logic valid /* driven by host */;
+  logic [8:1] addr;
+  modport host (input clk, output req, valid, addr, input rsp),
+          device (input clk, req, valid, addr, output rsp);
+endinterface
+";
+
+        let parsed = parse_interface(text).unwrap();
+        assert_eq!(parsed.name, "bus_if");
+        assert_eq!(
+            parsed.signals,
+            IndexMap::from([
+                ("clk".to_string(), 1),
+                ("req".to_string(), 8),
+                ("rsp".to_string(), 8),
+                ("valid".to_string(), 1),
+                ("addr".to_string(), 8),
+            ])
+        );
+
+        let directions = |modport: &str| -> Vec<(&str, &str)> {
+            parsed.modports[modport]
+                .iter()
+                .map(|(signal, direction)| (signal.as_str(), direction.as_str()))
+                .collect()
+        };
+        assert_eq!(
+            directions("host"),
+            vec![
+                ("clk", "input"),
+                ("req", "output"),
+                ("valid", "output"),
+                ("addr", "output"),
+                ("rsp", "input"),
+            ]
+        );
+        assert_eq!(
+            directions("device"),
+            vec![
+                ("clk", "input"),
+                ("req", "input"),
+                ("valid", "input"),
+                ("addr", "input"),
+                ("rsp", "output"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_interface_not_found() {
+        assert_eq!(parse_interface("module A; endmodule"), None);
+    }
+}
@@ -4600,4 +4600,2462 @@ endmodule
 "
         );
     }
+
+    #[test]
+    fn test_intf_diff() {
+        let a_mod_def = ModDef::new("A");
+        a_mod_def.add_port("a_data", IO::Output(8));
+        a_mod_def.add_port("a_valid", IO::Output(1));
+        a_mod_def.add_port("a_last", IO::Output(1));
+        let a_intf = a_mod_def.def_intf_from_prefix("a", "a_");
+
+        let b_mod_def = ModDef::new("B");
+        b_mod_def.add_port("b_data", IO::Input(16));
+        b_mod_def.add_port("b_valid", IO::Input(1));
+        b_mod_def.add_port("b_ready", IO::Output(1));
+        let b_intf = b_mod_def.def_intf_from_prefix("b", "b_");
+
+        let (only_in_a, only_in_b, width_mismatch) = a_intf.diff(&b_intf);
+        assert_eq!(only_in_a, vec!["last".to_string()]);
+        assert_eq!(only_in_b, vec!["ready".to_string()]);
+        assert_eq!(width_mismatch, vec!["data".to_string()]);
+
+        assert_eq!(a_intf.diff(&a_intf), (vec![], vec![], vec![]));
+    }
+
+    #[test]
+    fn test_wrap_with_pipeline() {
+        let a_mod_def = ModDef::new("A");
+        a_mod_def.add_port("a_data", IO::Output(8));
+        a_mod_def.add_port("a_ready", IO::Input(1));
+        a_mod_def.def_intf_from_prefix("x", "a_");
+        a_mod_def.set_usage(Usage::EmitStubAndStop);
+
+        let b_mod_def = ModDef::new("B");
+        b_mod_def.add_port("b_data", IO::Input(8));
+        b_mod_def.add_port("b_ready", IO::Output(1));
+        b_mod_def.def_intf_from_prefix("x", "b_");
+        b_mod_def.set_usage(Usage::EmitStubAndStop);
+
+        let top = ModDef::new("top");
+        let a_inst = top.instantiate(&a_mod_def, Some("a"), None);
+        let b_inst = top.instantiate(&b_mod_def, Some("b"), None);
+        a_inst.get_intf("x").connect(&b_inst.get_intf("x"), false);
+
+        top.wrap_with_pipeline(
+            &a_inst,
+            &b_inst,
+            "x",
+            PipelineConfig {
+                clk: "clk".to_string(),
+                depth: 2,
+            },
+        );
+
+        assert_eq!(
+            top.emit(true),
+            "\
+module A(
+  output wire [7:0] a_data,
+  input wire a_ready
+);
+
+endmodule
+module B(
+  input wire [7:0] b_data,
+  output wire b_ready
+);
+
+endmodule
+module top_a_b_x_pipeline(
+  input wire clk,
+  input wire [7:0] a_data,
+  output wire [7:0] b_data,
+  output wire a_ready,
+  input wire b_ready
+);
+  br_delay_nr #(
+    .Width(32'h0000_0008),
+    .NumStages(32'h0000_0002)
+  ) pipeline_conn_0 (
+    .clk(clk),
+    .in(a_data[7:0]),
+    .out(b_data[7:0]),
+    .out_stages()
+  );
+  br_delay_nr #(
+    .Width(32'h0000_0001),
+    .NumStages(32'h0000_0002)
+  ) pipeline_conn_1 (
+    .clk(clk),
+    .in(b_ready),
+    .out(a_ready),
+    .out_stages()
+  );
+endmodule
+module top(
+  input wire clk
+);
+  wire [7:0] a_a_data;
+  wire a_a_ready;
+  wire [7:0] b_b_data;
+  wire b_b_ready;
+  wire a_b_x_pipeline_i_clk;
+  wire [7:0] a_b_x_pipeline_i_a_data;
+  wire [7:0] a_b_x_pipeline_i_b_data;
+  wire a_b_x_pipeline_i_a_ready;
+  wire a_b_x_pipeline_i_b_ready;
+  A a (
+    .a_data(a_a_data),
+    .a_ready(a_a_ready)
+  );
+  B b (
+    .b_data(b_b_data),
+    .b_ready(b_b_ready)
+  );
+  top_a_b_x_pipeline a_b_x_pipeline_i (
+    .clk(a_b_x_pipeline_i_clk),
+    .a_data(a_b_x_pipeline_i_a_data),
+    .b_data(a_b_x_pipeline_i_b_data),
+    .a_ready(a_b_x_pipeline_i_a_ready),
+    .b_ready(a_b_x_pipeline_i_b_ready)
+  );
+  assign a_b_x_pipeline_i_clk = clk;
+  assign a_b_x_pipeline_i_a_data[7:0] = a_a_data[7:0];
+  assign a_a_ready = a_b_x_pipeline_i_a_ready;
+  assign b_b_data[7:0] = a_b_x_pipeline_i_b_data[7:0];
+  assign a_b_x_pipeline_i_b_ready = b_b_ready;
+endmodule
+"
+        );
+    }
+
+    #[test]
+    fn test_get_port_connections_summary() {
+        let leaf = ModDef::new("Leaf");
+        leaf.add_port("in", IO::Input(4));
+        leaf.set_usage(Usage::EmitStubAndStop);
+
+        let top = ModDef::new("Top");
+        let a = top.add_port("a", IO::Input(8));
+        let b = top.add_port("b", IO::Output(8));
+        top.add_port("c", IO::Input(2));
+        let inst = top.instantiate(&leaf, None, None);
+
+        a.slice(3, 0).connect(&inst.get_port("in"));
+        a.slice(7, 4).connect(&b.slice(3, 0));
+        b.slice(7, 4).tieoff(0);
+
+        let summary = top.get_port_connections_summary();
+        assert_eq!(
+            summary,
+            vec![
+                PortConnectionSummary {
+                    port_name: "a".to_string(),
+                    total_bits: 8,
+                    connected_bits: 8,
+                    tiedoff_bits: 0,
+                    unused_bits: 0,
+                },
+                PortConnectionSummary {
+                    port_name: "b".to_string(),
+                    total_bits: 8,
+                    connected_bits: 4,
+                    tiedoff_bits: 4,
+                    unused_bits: 0,
+                },
+                PortConnectionSummary {
+                    port_name: "c".to_string(),
+                    total_bits: 2,
+                    connected_bits: 0,
+                    tiedoff_bits: 0,
+                    unused_bits: 0,
+                },
+            ]
+        );
+
+        top.get_port("c").unused();
+        assert_eq!(top.get_port_connections_summary()[2].unused_bits, 2);
+    }
+
+    #[test]
+    fn test_intf_connect_partial() {
+        let a_mod_def = ModDef::new("A");
+        a_mod_def.add_port("a_data", IO::Output(8));
+        a_mod_def.add_port("a_valid", IO::Output(1));
+        a_mod_def.add_port("a_last", IO::Output(1));
+        a_mod_def.def_intf_from_prefix("a", "a_");
+        a_mod_def.set_usage(Usage::EmitStubAndStop);
+
+        let b_mod_def = ModDef::new("B");
+        b_mod_def.add_port("b_data", IO::Input(8));
+        b_mod_def.add_port("b_valid", IO::Input(1));
+        b_mod_def.def_intf_from_prefix("b", "b_");
+        b_mod_def.set_usage(Usage::EmitStubAndStop);
+
+        let top = ModDef::new("Top");
+        let a_inst = top.instantiate(&a_mod_def, None, None);
+        let b_inst = top.instantiate(&b_mod_def, None, None);
+
+        let (matched, unmatched) = a_inst.get_intf("a").connect_partial(&b_inst.get_intf("b"));
+        assert_eq!(matched, 2);
+        assert_eq!(unmatched, 1);
+
+        a_inst.get_port("a_last").unused();
+
+        assert_eq!(
+            top.emit(true),
+            "\
+module A(
+  output wire [7:0] a_data,
+  output wire a_valid,
+  output wire a_last
+);
+
+endmodule
+module B(
+  input wire [7:0] b_data,
+  input wire b_valid
+);
+
+endmodule
+module Top;
+  wire [7:0] A_i_a_data;
+  wire A_i_a_valid;
+  wire A_i_a_last;
+  wire [7:0] B_i_b_data;
+  wire B_i_b_valid;
+  A A_i (
+    .a_data(A_i_a_data),
+    .a_valid(A_i_a_valid),
+    .a_last(A_i_a_last)
+  );
+  B B_i (
+    .b_data(B_i_b_data),
+    .b_valid(B_i_b_valid)
+  );
+  assign B_i_b_data[7:0] = A_i_a_data[7:0];
+  assign B_i_b_valid = A_i_a_valid;
+endmodule
+"
+        );
+    }
+
+    #[test]
+    fn test_from_verilog_all() {
+        let verilog = "
+        module A(
+          input [7:0] a_in,
+          output a_out
+        );
+        endmodule
+        module B(
+          output [3:0] b_out
+        );
+        endmodule
+        ";
+
+        let mod_defs = ModDef::from_verilog_all(verilog, false);
+        let names: Vec<String> = mod_defs.iter().map(|m| m.get_name()).collect();
+        assert_eq!(names, vec!["A".to_string(), "B".to_string()]);
+
+        assert!(matches!(mod_defs[0].get_port("a_in").io(), IO::Input(8)));
+        assert!(matches!(mod_defs[0].get_port("a_out").io(), IO::Output(1)));
+        assert!(matches!(mod_defs[1].get_port("b_out").io(), IO::Output(4)));
+    }
+
+    #[test]
+    fn test_add_feedthrough_named() {
+        let mod_def = ModDef::new("TestModule");
+        let (in_port, out_port) = mod_def.add_feedthrough_named("ft_in", "ft_out", 4);
+        assert_eq!(in_port.name(), "ft_in");
+        assert_eq!(out_port.name(), "ft_out");
+
+        assert_eq!(
+            mod_def.emit(true),
+            "\
+module TestModule(
+  input wire [3:0] ft_in,
+  output wire [3:0] ft_out
+);
+  assign ft_out[3:0] = ft_in[3:0];
+endmodule
+"
+        );
+    }
+
+    #[test]
+    fn test_port_slice_is_same_port() {
+        let leaf = ModDef::new("Leaf");
+        leaf.add_port("x", IO::Input(8));
+
+        let top = ModDef::new("Top");
+        let a = top.add_port("a", IO::Input(8));
+        let b = top.add_port("b", IO::Input(8));
+        let inst_0 = top.instantiate(&leaf, Some("inst_0"), None);
+        let inst_1 = top.instantiate(&leaf, Some("inst_1"), None);
+
+        assert!(a.slice(3, 0).is_same_port(&a.slice(7, 4)));
+        assert!(a.slice(3, 0).is_same_port(&top.get_port("a").bit(0)));
+        assert!(!a.slice(3, 0).is_same_port(&b.slice(3, 0)));
+
+        let x_0 = inst_0.get_port("x");
+        assert!(x_0.slice(1, 0).is_same_port(&x_0.slice(7, 2)));
+        assert!(!x_0.bit(0).is_same_port(&inst_1.get_port("x").bit(0)));
+        assert!(!x_0.bit(0).is_same_port(&leaf.get_port("x").bit(0)));
+    }
+
+    #[test]
+    fn test_funnel_connect_with_offset() {
+        let module_a = ModDef::new("ModuleA");
+        module_a.add_port("a_data", IO::Output(2));
+        module_a.add_port("a_flag", IO::Output(4));
+        module_a.add_port("a_ready", IO::Input(1));
+        module_a.set_usage(Usage::EmitStubAndStop);
+
+        let module_c = ModDef::new("ModuleC");
+        module_c.add_port("c_data", IO::Input(2));
+        module_c.add_port("c_flag", IO::Input(4));
+        module_c.add_port("c_ready", IO::Output(1));
+        module_c.set_usage(Usage::EmitStubAndStop);
+
+        let module_b = ModDef::new("ModuleB");
+        module_b.feedthrough("ft_left_i", "ft_right_o", 8);
+        module_b.feedthrough("ft_right_i", "ft_left_o", 1);
+
+        let top_module = ModDef::new("TopModule");
+        let a_inst = top_module.instantiate(&module_a, None, None);
+        let b_inst = top_module.instantiate(&module_b, None, None);
+        let c_inst = top_module.instantiate(&module_c, None, None);
+
+        let mut funnel = Funnel::new(
+            (b_inst.get_port("ft_left_i"), b_inst.get_port("ft_left_o")),
+            (b_inst.get_port("ft_right_i"), b_inst.get_port("ft_right_o")),
+        );
+
+        funnel.connect_with_offset(&a_inst.get_port("a_data"), &c_inst.get_port("c_data"), 4, 4);
+        // The flag fits below the data, so it is placed at offset 0.
+        funnel.connect(&a_inst.get_port("a_flag"), &c_inst.get_port("c_flag"));
+        funnel.connect(&a_inst.get_port("a_ready"), &c_inst.get_port("c_ready"));
+        funnel.done();
+
+        assert_eq!(
+            top_module.emit(true),
+            "\
+module ModuleA(
+  output wire [1:0] a_data,
+  output wire [3:0] a_flag,
+  input wire a_ready
+);
+
+endmodule
+module ModuleB(
+  input wire [7:0] ft_left_i,
+  output wire [7:0] ft_right_o,
+  input wire ft_right_i,
+  output wire ft_left_o
+);
+  assign ft_right_o[7:0] = ft_left_i[7:0];
+  assign ft_left_o = ft_right_i;
+endmodule
+module ModuleC(
+  input wire [1:0] c_data,
+  input wire [3:0] c_flag,
+  output wire c_ready
+);
+
+endmodule
+module TopModule;
+  wire [1:0] ModuleA_i_a_data;
+  wire [3:0] ModuleA_i_a_flag;
+  wire ModuleA_i_a_ready;
+  wire [7:0] ModuleB_i_ft_left_i;
+  wire [7:0] ModuleB_i_ft_right_o;
+  wire ModuleB_i_ft_right_i;
+  wire ModuleB_i_ft_left_o;
+  wire [1:0] ModuleC_i_c_data;
+  wire [3:0] ModuleC_i_c_flag;
+  wire ModuleC_i_c_ready;
+  ModuleA ModuleA_i (
+    .a_data(ModuleA_i_a_data),
+    .a_flag(ModuleA_i_a_flag),
+    .a_ready(ModuleA_i_a_ready)
+  );
+  ModuleB ModuleB_i (
+    .ft_left_i(ModuleB_i_ft_left_i),
+    .ft_right_o(ModuleB_i_ft_right_o),
+    .ft_right_i(ModuleB_i_ft_right_i),
+    .ft_left_o(ModuleB_i_ft_left_o)
+  );
+  ModuleC ModuleC_i (
+    .c_data(ModuleC_i_c_data),
+    .c_flag(ModuleC_i_c_flag),
+    .c_ready(ModuleC_i_c_ready)
+  );
+  assign ModuleB_i_ft_left_i[5:4] = ModuleA_i_a_data[1:0];
+  assign ModuleC_i_c_data[1:0] = ModuleB_i_ft_right_o[5:4];
+  assign ModuleB_i_ft_left_i[3:0] = ModuleA_i_a_flag[3:0];
+  assign ModuleC_i_c_flag[3:0] = ModuleB_i_ft_right_o[3:0];
+  assign ModuleA_i_a_ready = ModuleB_i_ft_left_o;
+  assign ModuleB_i_ft_right_i = ModuleC_i_c_ready;
+  assign ModuleB_i_ft_left_i[7:6] = 2'h0;
+endmodule
+"
+        );
+    }
+
+    #[test]
+    fn test_get_named_net() {
+        let leaf = ModDef::new("Leaf");
+        leaf.add_port("x", IO::Input(8));
+        leaf.add_port("y", IO::Output(8));
+
+        let top = ModDef::new("Top");
+        let a = top.add_port("a", IO::Input(8));
+        let inst = top.instantiate(&leaf, Some("inst"), None);
+        inst.get_port("y").slice(3, 0).connect_to_net("custom");
+        inst.get_port("y").slice(7, 4).unused();
+        a.connect(&inst.get_port("x"));
+
+        assert!(top
+            .get_named_net("a")
+            .unwrap()
+            .is_same_port(&a.to_port_slice()));
+        assert!(top
+            .get_named_net("custom")
+            .unwrap()
+            .is_same_port(&inst.get_port("y").to_port_slice()));
+        assert!(top
+            .get_named_net("inst_x")
+            .unwrap()
+            .is_same_port(&inst.get_port("x").to_port_slice()));
+        assert!(top.get_named_net("inst_z").is_none());
+        assert!(top.get_named_net("b").is_none());
+    }
+
+    #[test]
+    fn test_add_parameter() {
+        let leaf = ModDef::new("Leaf");
+        leaf.add_port("x", IO::Input(8));
+        leaf.add_parameter("WIDTH", "", "8");
+        leaf.add_parameter("DEPTH", "int", "1");
+        leaf.set_usage(Usage::EmitStubAndStop);
+
+        let top = ModDef::new("Top");
+        top.add_parameter("WIDTH", "int", "8");
+        let a = top.add_port("a", IO::Input(8));
+        let inst = top.instantiate(&leaf, Some("inst"), None);
+        inst.set_parameter("WIDTH", "WIDTH");
+        inst.set_parameter("DEPTH", "WIDTH / 2");
+        a.connect(&inst.get_port("x"));
+
+        assert_eq!(
+            top.emit(true),
+            "\
+module Leaf #(
+  parameter WIDTH = 8,
+  parameter int DEPTH = 1
+) (
+  input wire [7:0] x
+);
+
+endmodule
+module Top #(
+  parameter int WIDTH = 8
+) (
+  input wire [7:0] a
+);
+  wire [7:0] inst_x;
+  Leaf #(
+    .WIDTH(WIDTH),
+    .DEPTH(WIDTH / 2)
+  ) inst (
+    .x(inst_x)
+  );
+  assign inst_x[7:0] = a[7:0];
+endmodule
+"
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Parameter WIDTH does not exist on module definition Leaf")]
+    fn test_set_parameter_undeclared() {
+        let leaf = ModDef::new("Leaf");
+        leaf.add_port("x", IO::Input(8));
+
+        let top = ModDef::new("Top");
+        let inst = top.instantiate(&leaf, Some("inst"), None);
+        inst.set_parameter("WIDTH", "8");
+    }
+
+    #[test]
+    fn test_set_port_width_expr() {
+        let gen = ModDef::new("Gen");
+        gen.add_parameter("WIDTH", "int", "8");
+        let a = gen.add_port("a", IO::Input(8));
+        let b = gen.add_port("b", IO::Output(8));
+        let c = gen.add_port("c", IO::Output(8));
+        gen.set_port_width_expr("a", "WIDTH");
+        gen.set_port_width_expr("b", "WIDTH");
+        gen.set_port_width_expr("c", "WIDTH");
+        a.connect(&b);
+        c.tieoff(0);
+
+        let top = ModDef::new("Top");
+        let x = top.add_port("x", IO::Input(8));
+        let y = top.add_port("y", IO::Output(8));
+        let gen_inst = top.instantiate(&gen, Some("gen"), None);
+        gen_inst.set_parameter("WIDTH", "8");
+        x.connect(&gen_inst.get_port("a"));
+        y.connect(&gen_inst.get_port("b"));
+        gen_inst.get_port("c").unused();
+
+        assert_eq!(
+            top.emit(true),
+            "\
+module Gen #(
+  parameter int WIDTH = 8
+) (
+  input wire [WIDTH-1:0] a,
+  output wire [WIDTH-1:0] b,
+  output wire [WIDTH-1:0] c
+);
+  assign b[WIDTH-1:0] = a[WIDTH-1:0];
+  assign c[WIDTH-1:0] = (WIDTH)'(8'h00);
+endmodule
+module Top(
+  input wire [7:0] x,
+  output wire [7:0] y
+);
+  wire [7:0] gen_a;
+  wire [7:0] gen_b;
+  wire [7:0] gen_c;
+  Gen #(
+    .WIDTH(8)
+  ) gen (
+    .a(gen_a),
+    .b(gen_b),
+    .c(gen_c)
+  );
+  assign gen_a[7:0] = x[7:0];
+  assign y[7:0] = gen_b[7:0];
+endmodule
+"
+        );
+    }
+
+    #[test]
+    fn test_get_connected_port_names() {
+        let leaf = ModDef::new("Leaf");
+        leaf.add_port("x", IO::Input(8));
+        leaf.add_port("y", IO::Output(8));
+        leaf.set_usage(Usage::EmitStubAndStop);
+
+        let top = ModDef::new("Top");
+        let a = top.add_port("a", IO::Input(8));
+        let b = top.add_port("b", IO::Output(4));
+        let inst_0 = top.instantiate(&leaf, Some("inst_0"), None);
+        let inst_1 = top.instantiate(&leaf, Some("inst_1"), None);
+
+        a.connect(&inst_0.get_port("x"));
+        a.connect(&inst_1.get_port("x"));
+        inst_0.get_port("y").connect_to_net("shared");
+        inst_1.get_port("y").slice(3, 0).connect(&b);
+        inst_1.get_port("y").slice(7, 4).unused();
+
+        assert_eq!(a.get_connected_port_names(), vec!["x"]);
+        assert_eq!(inst_1.get_port("y").get_connected_port_names(), vec!["b"]);
+        assert_eq!(b.get_connected_port_names(), vec!["y"]);
+        assert!(inst_0.get_port("y").get_connected_port_names().is_empty());
+
+        let sink = ModDef::new("Sink");
+        sink.add_port("z", IO::Input(8));
+        sink.set_usage(Usage::EmitStubAndStop);
+        let sink_inst = top.instantiate(&sink, Some("sink"), None);
+        sink_inst.get_port("z").connect_to_net("shared");
+
+        assert_eq!(inst_0.get_port("y").get_connected_port_names(), vec!["z"]);
+        assert_eq!(
+            sink_inst.get_port("z").get_connected_port_names(),
+            vec!["y"]
+        );
+    }
+
+    #[test]
+    fn test_try_get_intf() {
+        let module = ModDef::new("Module");
+        module.add_port("a_data", IO::Input(8));
+        module.add_port("a_valid", IO::Input(1));
+        module.def_intf_from_prefix("a", "a_");
+
+        assert!(module.try_get_intf("a").is_some());
+        assert!(module.try_get_intf("b").is_none());
+    }
+
+    #[test]
+    fn test_port_slice_direction() {
+        let module = ModDef::new("Module");
+        let a = module.add_port("a", IO::Input(8));
+        let b = module.add_port("b", IO::Output(8));
+        let c = module.add_port("c", IO::InOut(8));
+
+        assert!(a.slice(3, 0).is_input());
+        assert!(!a.slice(3, 0).is_output());
+        assert!(b.slice(7, 4).is_output());
+        assert!(!b.slice(7, 4).is_inout());
+        assert!(c.bit(0).is_inout());
+        assert!(!c.bit(0).is_input());
+    }
+
+    #[test]
+    fn test_emit_sdc() {
+        let module = ModDef::new("Module");
+        module.add_port("clk", IO::Input(1));
+        module.add_port("a", IO::Input(8));
+        module.add_port("b", IO::Output(8));
+        module.add_port("c", IO::InOut(1));
+        module.set_port_timing_constraint("clk", 2.5);
+        module.set_port_timing_constraint("b", 0.4);
+
+        assert_eq!(
+            module.emit_sdc(),
+            "\
+create_clock -name clk -period 2.5 [get_ports clk]
+set_input_delay 0 -clock clk [get_ports a]
+set_output_delay 0.4 -clock clk [get_ports b]
+set_input_delay 0 -clock clk [get_ports c]
+set_output_delay 0 -clock clk [get_ports c]
+"
+        );
+    }
+
+    #[test]
+    fn test_get_hierarchy_depth() {
+        let leaf = ModDef::new("Leaf");
+        let mid = ModDef::new("Mid");
+        mid.instantiate(&leaf, None, None);
+        let stopped = ModDef::new("Stopped");
+        stopped.instantiate(&mid, None, None);
+        stopped.set_usage(Usage::EmitStubAndStop);
+
+        let top = ModDef::new("Top");
+        top.instantiate(&leaf, None, None);
+        top.instantiate(&mid, None, None);
+        top.instantiate(&stopped, None, None);
+
+        assert_eq!(leaf.get_hierarchy_depth(), 0);
+        assert_eq!(mid.get_hierarchy_depth(), 1);
+        assert_eq!(stopped.get_hierarchy_depth(), 0);
+        assert_eq!(top.get_hierarchy_depth(), 2);
+    }
+
+    #[test]
+    fn test_emit_to_writer() {
+        let module = ModDef::new("Module");
+        module.feedthrough("a", "b", 8);
+
+        let mut buffer: Vec<u8> = Vec::new();
+        module.emit_to_writer(&mut buffer, true).unwrap();
+
+        assert_eq!(String::from_utf8(buffer).unwrap(), module.emit(true));
+    }
+
+    #[test]
+    fn test_intf_mirror() {
+        let module = ModDef::new("Module");
+        module.add_port("bus_data", IO::Output(8));
+        module.add_port("bus_valid", IO::Output(1));
+        module.add_port("bus_ready", IO::Input(1));
+        module.add_port("bus_pad", IO::InOut(1));
+        module.add_port("other", IO::Input(1));
+        let (mirrored, mirrored_intf) = module.def_intf_from_prefix("bus", "bus_").mirror();
+
+        assert_eq!(
+            mirrored.emit(false),
+            "\
+module Module_mirrored(
+  input wire [7:0] bus_data,
+  input wire bus_valid,
+  output wire bus_ready,
+  inout wire bus_pad,
+  input wire other
+);
+
+endmodule
+"
+        );
+        assert_eq!(
+            mirrored_intf.directionality(),
+            IntfDirectionality::Bidirectional
+        );
+
+        let valid = module.def_intf_from_prefix("valid", "bus_valid");
+        assert_eq!(valid.directionality(), IntfDirectionality::AllOutput);
+        let (_valid_module, mirrored_valid) = valid.mirror();
+        assert_eq!(
+            mirrored_valid.directionality(),
+            IntfDirectionality::AllInput
+        );
+
+        // The original module definition is unchanged.
+        assert_eq!(module.get_port("bus_data").io(), IO::Output(8));
+        assert_eq!(module.get_port("bus_ready").io(), IO::Input(1));
+    }
+
+    #[test]
+    fn test_port_slice_get_driver() {
+        let leaf = ModDef::new("Leaf");
+        leaf.add_port("x", IO::Input(8));
+        leaf.add_port("y", IO::Output(8));
+        leaf.set_usage(Usage::EmitStubAndStop);
+
+        let top = ModDef::new("Top");
+        let a = top.add_port("a", IO::Input(8));
+        let b = top.add_port("b", IO::Output(8));
+        let inst_0 = top.instantiate(&leaf, Some("inst_0"), None);
+        let inst_1 = top.instantiate(&leaf, Some("inst_1"), None);
+
+        a.connect(&inst_0.get_port("x"));
+        inst_0.get_port("y").connect_to_net("net");
+        inst_1.get_port("x").connect_to_net("net");
+        inst_1.get_port("y").slice(3, 0).connect(&b.slice(7, 4));
+        b.slice(3, 0).tieoff(0);
+
+        let driver = inst_0.get_port("x").bit(5).get_driver().unwrap();
+        assert!(driver.is_same_port(&a.to_port_slice()));
+        assert_eq!(format!("{:?}", driver), format!("{:?}", a.bit(5)));
+
+        let driver = inst_1.get_port("x").slice(7, 4).get_driver().unwrap();
+        assert_eq!(
+            format!("{:?}", driver),
+            format!("{:?}", inst_0.get_port("y").slice(7, 4))
+        );
+
+        let driver = b.slice(6, 5).get_driver().unwrap();
+        assert_eq!(
+            format!("{:?}", driver),
+            format!("{:?}", inst_1.get_port("y").slice(2, 1))
+        );
+
+        assert!(b.slice(3, 0).get_driver().is_none());
+        assert!(a.bit(0).get_driver().is_none());
+    }
+
+    #[test]
+    #[should_panic(expected = "does not have a uniform driver")]
+    fn test_port_slice_get_driver_non_uniform() {
+        let top = ModDef::new("Top");
+        let a = top.add_port("a", IO::Input(8));
+        let b = top.add_port("b", IO::Output(8));
+        a.slice(3, 0).connect(&b.slice(3, 0));
+        b.slice(7, 4).tieoff(0);
+        b.to_port_slice().get_driver();
+    }
+
+    #[test]
+    fn test_get_unused_interfaces() {
+        let module = ModDef::new("Module");
+        module.add_port("a_data", IO::Input(8));
+        module.add_port("a_valid", IO::Input(1));
+        module.add_port("b_data", IO::Output(8));
+        module.add_port("b_valid", IO::Output(1));
+        module.add_port("c_data", IO::Output(8));
+        module.add_port("d_data", IO::Input(8));
+        module.def_intf_from_prefix("a", "a_");
+        module.def_intf_from_prefix("b", "b_");
+        module.def_intf_from_prefix("c", "c_");
+        module.def_intf_from_prefix("d", "d_");
+
+        module
+            .get_port("a_valid")
+            .connect(&module.get_port("b_valid"));
+        module.get_port("d_data").unused();
+
+        assert_eq!(module.get_unused_interfaces(), vec!["c".to_string()]);
+    }
+
+    #[test]
+    fn test_assert_port_widths() {
+        let module = ModDef::new("Module");
+        module.add_port("a", IO::Input(8));
+        module.add_port("b", IO::Output(16));
+        module.assert_port_widths(&[("a", 8), ("b", 16)]);
+    }
+
+    #[test]
+    #[should_panic(expected = "Port width mismatches:
+Module.a: expected width 4, actual width 8
+Module.c: expected width 1, but port does not exist")]
+    fn test_assert_port_widths_mismatch() {
+        let module = ModDef::new("Module");
+        module.add_port("a", IO::Input(8));
+        module.add_port("b", IO::Output(16));
+        module.assert_port_widths(&[("a", 4), ("b", 16), ("c", 1)]);
+    }
+
+    #[test]
+    fn test_mod_def_diff() {
+        let leaf_a = ModDef::new("LeafA");
+        leaf_a.add_port("x", IO::Input(8));
+        let leaf_b = ModDef::new("LeafB");
+        leaf_b.add_port("x", IO::Input(8));
+
+        let old = ModDef::new("Top");
+        old.add_port("a", IO::Input(8));
+        old.add_port("b", IO::Input(8));
+        old.add_port("c", IO::Output(4));
+        let inst = old.instantiate(&leaf_a, Some("inst"), None);
+        old.instantiate(&leaf_a, Some("gone"), None);
+        old.get_port("a").connect(&inst.get_port("x"));
+
+        let new = ModDef::new("Top");
+        new.add_port("a", IO::Input(8));
+        new.add_port("b", IO::Input(16));
+        new.add_port("d", IO::Output(1));
+        let inst = new.instantiate(&leaf_b, Some("inst"), None);
+        new.instantiate(&leaf_a, Some("extra"), None);
+        inst.get_port("x").tieoff(0);
+
+        assert!(old.diff(&old).is_empty());
+        assert_eq!(
+            old.diff(&new),
+            vec![
+                "Port b changed from Input(8) to Input(16)",
+                "Port c removed",
+                "Port d added: Output(1)",
+                "Instance inst changed from LeafA to LeafB",
+                "Instance gone removed",
+                "Instance extra added: LeafA",
+                "Connection removed: inst.x[7:0] = a[7:0]",
+                "Connection added: inst.x[7:0] tied off to 0",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_add_wrapper_port_and_connect() {
+        let leaf = ModDef::new("Leaf");
+        leaf.add_port("x", IO::Input(8));
+        leaf.add_port("y", IO::Output(4));
+        leaf.set_usage(Usage::EmitStubAndStop);
+
+        let top = ModDef::new("Top");
+        let inst = top.instantiate(&leaf, Some("inst"), None);
+        let x = top.add_wrapper_port_and_connect(&inst, "x");
+        let y = top.add_wrapper_port_and_connect(&inst, "y");
+        assert!(matches!(x.io(), IO::Input(8)));
+        assert!(matches!(y.io(), IO::Output(4)));
+
+        assert_eq!(
+            top.emit(true),
+            "\
+module Leaf(
+  input wire [7:0] x,
+  output wire [3:0] y
+);
+
+endmodule
+module Top(
+  input wire [7:0] x,
+  output wire [3:0] y
+);
+  wire [7:0] inst_x;
+  wire [3:0] inst_y;
+  Leaf inst (
+    .x(inst_x),
+    .y(inst_y)
+  );
+  assign inst_x[7:0] = x[7:0];
+  assign y[3:0] = inst_y[3:0];
+endmodule
+"
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "is not an instance within module definition Other")]
+    fn test_add_wrapper_port_and_connect_wrong_parent() {
+        let leaf = ModDef::new("Leaf");
+        leaf.add_port("x", IO::Input(8));
+        let top = ModDef::new("Top");
+        let inst = top.instantiate(&leaf, Some("inst"), None);
+        let other = ModDef::new("Other");
+        other.add_wrapper_port_and_connect(&inst, "x");
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "Port z does not exist on instance Top.inst of module definition Leaf. Available ports: x, y"
+    )]
+    fn test_mod_inst_get_port_missing() {
+        let leaf = ModDef::new("Leaf");
+        leaf.add_port("x", IO::Input(8));
+        leaf.add_port("y", IO::Output(8));
+        let top = ModDef::new("Top");
+        let inst = top.instantiate(&leaf, Some("inst"), None);
+        inst.get_port_slice("z", 3, 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid slice [8:0] of port Top.inst.x, which has width 8")]
+    fn test_mod_inst_get_port_slice_out_of_range() {
+        let leaf = ModDef::new("Leaf");
+        leaf.add_port("x", IO::Input(8));
+        let top = ModDef::new("Top");
+        let inst = top.instantiate(&leaf, Some("inst"), None);
+        inst.get_port_slice("x", 8, 0);
+    }
+
+    #[test]
+    fn test_export_all_unconnected() {
+        let leaf = ModDef::new("Leaf");
+        leaf.add_port("x", IO::Input(8));
+        leaf.add_port("y", IO::Output(8));
+        leaf.set_usage(Usage::EmitStubAndStop);
+
+        let top = ModDef::new("Top");
+        let inst_a = top.instantiate(&leaf, Some("inst_a"), None);
+        let inst_b = top.instantiate(&leaf, Some("inst_b"), None);
+        inst_a.get_port("y").connect(&inst_b.get_port("x"));
+
+        let exported = top.export_all_unconnected("io_");
+        let names: Vec<&str> = exported.iter().map(|port| port.name()).collect();
+        assert_eq!(names, vec!["io_inst_a_x", "io_inst_b_y"]);
+
+        assert_eq!(
+            top.emit(true),
+            "\
+module Leaf(
+  input wire [7:0] x,
+  output wire [7:0] y
+);
+
+endmodule
+module Top(
+  input wire [7:0] io_inst_a_x,
+  output wire [7:0] io_inst_b_y
+);
+  wire [7:0] inst_a_x;
+  wire [7:0] inst_a_y;
+  wire [7:0] inst_b_x;
+  wire [7:0] inst_b_y;
+  Leaf inst_a (
+    .x(inst_a_x),
+    .y(inst_a_y)
+  );
+  Leaf inst_b (
+    .x(inst_b_x),
+    .y(inst_b_y)
+  );
+  assign inst_b_x[7:0] = inst_a_y[7:0];
+  assign inst_a_x[7:0] = io_inst_a_x[7:0];
+  assign io_inst_b_y[7:0] = inst_b_y[7:0];
+endmodule
+"
+        );
+    }
+
+    #[test]
+    fn test_intf_export_preserving_names() {
+        let leaf = ModDef::new("Leaf");
+        leaf.add_port("a_data", IO::Output(8));
+        leaf.add_port("a_valid", IO::Output(1));
+        leaf.def_intf_from_prefix("a", "a_");
+        leaf.set_usage(Usage::EmitStubAndStop);
+
+        let top = ModDef::new("Top");
+        let inst = top.instantiate(&leaf, Some("inst"), None);
+        inst.get_intf("a").export_preserving_names();
+        assert!(top.has_intf("a"));
+
+        assert_eq!(
+            top.emit(true),
+            "\
+module Leaf(
+  output wire [7:0] a_data,
+  output wire a_valid
+);
+
+endmodule
+module Top(
+  output wire [7:0] data,
+  output wire valid
+);
+  wire [7:0] inst_a_data;
+  wire inst_a_valid;
+  Leaf inst (
+    .a_data(inst_a_data),
+    .a_valid(inst_a_valid)
+  );
+  assign data[7:0] = inst_a_data[7:0];
+  assign valid = inst_a_valid;
+endmodule
+"
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "port(s) data already exist on module definition Top")]
+    fn test_intf_export_preserving_names_collision() {
+        let leaf = ModDef::new("Leaf");
+        leaf.add_port("a_data", IO::Output(8));
+        leaf.def_intf_from_prefix("a", "a_");
+
+        let top = ModDef::new("Top");
+        top.add_port("data", IO::Output(8));
+        let inst = top.instantiate(&leaf, Some("inst"), None);
+        inst.get_intf("a").export_preserving_names();
+    }
+
+    #[test]
+    fn test_emit_dot_graph() {
+        let leaf = ModDef::new("Leaf");
+        let mid = ModDef::new("Mid");
+        mid.instantiate(&leaf, Some("leaf_0"), None);
+        mid.instantiate(&leaf, Some("leaf_1"), None);
+        let top = ModDef::new("Top");
+        top.instantiate(&mid, Some("mid"), None);
+        top.instantiate(&leaf, Some("leaf"), None);
+
+        assert_eq!(
+            top.emit_dot_graph(false),
+            "\
+digraph \"Top\" {
+  \"Top\";
+  \"Top\" -> \"Mid\" [label=\"mid\"];
+  \"Top\" -> \"Leaf\" [label=\"leaf\"];
+}
+"
+        );
+
+        assert_eq!(
+            top.emit_dot_graph(true),
+            "\
+digraph \"Top\" {
+  \"Top\";
+  \"Top\" -> \"Mid\" [label=\"mid\"];
+  \"Top\" -> \"Leaf\" [label=\"leaf\"];
+  \"Mid\";
+  \"Mid\" -> \"Leaf\" [label=\"leaf_0\"];
+  \"Mid\" -> \"Leaf\" [label=\"leaf_1\"];
+  \"Leaf\";
+}
+"
+        );
+    }
+
+    #[test]
+    fn test_intf_export_chaining() {
+        let leaf = ModDef::new("Leaf");
+        leaf.add_port("a_data", IO::Output(8));
+        leaf.add_port("a_valid", IO::Output(1));
+        leaf.def_intf_from_prefix("a", "a_");
+        leaf.set_usage(Usage::EmitStubAndStop);
+
+        let mid = ModDef::new("Mid");
+        let leaf_inst = mid.instantiate(&leaf, Some("leaf"), None);
+        let mid_intf = leaf_inst.get_intf("a").export_with_prefix("b", "b_");
+        assert!(mid.has_intf("b"));
+        assert_eq!(
+            format!("{:?}", mid_intf),
+            format!("{:?}", mid.get_intf("b"))
+        );
+
+        let top = ModDef::new("Top");
+        let mid_inst = top.instantiate(&mid, Some("mid"), None);
+        let top_intf = mid_inst.get_intf("b").export_with_name_underscore("c");
+        assert_eq!(
+            format!("{:?}", top_intf),
+            format!("{:?}", top.get_intf("c"))
+        );
+        assert!(top.has_port("c_data"));
+        assert!(top.has_port("c_valid"));
+    }
+
+    #[test]
+    fn test_funnel_from_intfs() {
+        let build = |use_intfs: bool| {
+            let module_a = ModDef::new("ModuleA");
+            module_a.add_port("a_data", IO::Output(4));
+            module_a.add_port("a_ready", IO::Input(1));
+            module_a.set_usage(Usage::EmitStubAndStop);
+
+            let module_c = ModDef::new("ModuleC");
+            module_c.add_port("c_data", IO::Input(4));
+            module_c.add_port("c_ready", IO::Output(1));
+            module_c.set_usage(Usage::EmitStubAndStop);
+
+            let module_b = ModDef::new("ModuleB");
+            module_b.feedthrough("ft_left_i", "ft_right_o", 8);
+            module_b.feedthrough("ft_right_i", "ft_left_o", 1);
+            module_b.def_intf_from_prefix("left", "ft_left_");
+            module_b.def_intf_from_prefix("right", "ft_right_");
+
+            let top_module = ModDef::new("TopModule");
+            let a_inst = top_module.instantiate(&module_a, None, None);
+            let b_inst = top_module.instantiate(&module_b, None, None);
+            let c_inst = top_module.instantiate(&module_c, None, None);
+
+            let mut funnel = if use_intfs {
+                Funnel::from_intfs(&b_inst.get_intf("left"), &b_inst.get_intf("right"))
+            } else {
+                Funnel::new(
+                    (b_inst.get_port("ft_left_i"), b_inst.get_port("ft_left_o")),
+                    (b_inst.get_port("ft_right_i"), b_inst.get_port("ft_right_o")),
+                )
+            };
+
+            funnel.connect(&a_inst.get_port("a_data"), &c_inst.get_port("c_data"));
+            funnel.connect(&a_inst.get_port("a_ready"), &c_inst.get_port("c_ready"));
+            funnel.done();
+
+            top_module.emit(true)
+        };
+
+        assert_eq!(build(true), build(false));
+    }
+
+    #[test]
+    #[should_panic(expected = "must have exactly two signals, but has 3")]
+    fn test_funnel_from_intfs_wrong_size() {
+        let module = ModDef::new("Module");
+        module.add_port("a_i", IO::Input(8));
+        module.add_port("a_o", IO::Output(8));
+        module.add_port("a_x", IO::Output(8));
+        module.add_port("b_i", IO::Input(8));
+        module.add_port("b_o", IO::Output(8));
+        let a = module.def_intf_from_prefix("a", "a_");
+        let b = module.def_intf_from_prefix("b", "b_");
+        Funnel::from_intfs(&a, &b);
+    }
+
+    #[test]
+    fn test_funnel_connect_remaining_to() {
+        let module_a = ModDef::new("ModuleA");
+        module_a.add_port("a_data", IO::Output(2));
+        module_a.add_port("a_ready", IO::Input(1));
+        module_a.set_usage(Usage::EmitStubAndStop);
+
+        let module_c = ModDef::new("ModuleC");
+        module_c.add_port("c_data", IO::Input(2));
+        module_c.add_port("c_ready", IO::Output(1));
+        module_c.set_usage(Usage::EmitStubAndStop);
+
+        let module_b = ModDef::new("ModuleB");
+        module_b.feedthrough("ft_left_i", "ft_right_o", 8);
+        module_b.feedthrough("ft_right_i", "ft_left_o", 1);
+
+        let top_module = ModDef::new("TopModule");
+        let spare_in = top_module.add_port("spare_in", IO::Input(6));
+        let spare_out = top_module.add_port("spare_out", IO::Output(6));
+        let a_inst = top_module.instantiate(&module_a, None, None);
+        let b_inst = top_module.instantiate(&module_b, None, None);
+        let c_inst = top_module.instantiate(&module_c, None, None);
+
+        let mut funnel = Funnel::new(
+            (b_inst.get_port("ft_left_i"), b_inst.get_port("ft_left_o")),
+            (b_inst.get_port("ft_right_i"), b_inst.get_port("ft_right_o")),
+        );
+
+        funnel.connect_with_offset(&a_inst.get_port("a_data"), &c_inst.get_port("c_data"), 2, 2);
+        funnel.connect_a2b_remaining_to(&spare_in, &spare_out);
+        funnel.connect(&a_inst.get_port("a_ready"), &c_inst.get_port("c_ready"));
+        funnel.done();
+
+        assert_eq!(
+            top_module.emit(true),
+            "\
+module ModuleA(
+  output wire [1:0] a_data,
+  input wire a_ready
+);
+
+endmodule
+module ModuleB(
+  input wire [7:0] ft_left_i,
+  output wire [7:0] ft_right_o,
+  input wire ft_right_i,
+  output wire ft_left_o
+);
+  assign ft_right_o[7:0] = ft_left_i[7:0];
+  assign ft_left_o = ft_right_i;
+endmodule
+module ModuleC(
+  input wire [1:0] c_data,
+  output wire c_ready
+);
+
+endmodule
+module TopModule(
+  input wire [5:0] spare_in,
+  output wire [5:0] spare_out
+);
+  wire [1:0] ModuleA_i_a_data;
+  wire ModuleA_i_a_ready;
+  wire [7:0] ModuleB_i_ft_left_i;
+  wire [7:0] ModuleB_i_ft_right_o;
+  wire ModuleB_i_ft_right_i;
+  wire ModuleB_i_ft_left_o;
+  wire [1:0] ModuleC_i_c_data;
+  wire ModuleC_i_c_ready;
+  ModuleA ModuleA_i (
+    .a_data(ModuleA_i_a_data),
+    .a_ready(ModuleA_i_a_ready)
+  );
+  ModuleB ModuleB_i (
+    .ft_left_i(ModuleB_i_ft_left_i),
+    .ft_right_o(ModuleB_i_ft_right_o),
+    .ft_right_i(ModuleB_i_ft_right_i),
+    .ft_left_o(ModuleB_i_ft_left_o)
+  );
+  ModuleC ModuleC_i (
+    .c_data(ModuleC_i_c_data),
+    .c_ready(ModuleC_i_c_ready)
+  );
+  assign ModuleB_i_ft_left_i[3:2] = ModuleA_i_a_data[1:0];
+  assign ModuleC_i_c_data[1:0] = ModuleB_i_ft_right_o[3:2];
+  assign ModuleB_i_ft_left_i[1:0] = spare_in[1:0];
+  assign ModuleB_i_ft_left_i[7:4] = spare_in[5:2];
+  assign spare_out[1:0] = ModuleB_i_ft_right_o[1:0];
+  assign spare_out[5:2] = ModuleB_i_ft_right_o[7:4];
+  assign ModuleA_i_a_ready = ModuleB_i_ft_left_o;
+  assign ModuleB_i_ft_right_i = ModuleC_i_c_ready;
+endmodule
+"
+        );
+    }
+
+    #[test]
+    fn test_set_module_comment() {
+        let leaf = ModDef::new("Leaf");
+        leaf.add_port("x", IO::Input(1));
+        leaf.set_usage(Usage::EmitStubAndStop);
+        leaf.set_module_comment("Leaf stub");
+
+        let top = ModDef::new("Top");
+        top.set_module_comment("Generated by topstitch\nrevision 1");
+        let inst = top.instantiate(&leaf, Some("inst"), None);
+        inst.get_port("x").tieoff(0);
+
+        assert_eq!(
+            top.emit(true),
+            "\
+/*
+ * Leaf stub
+ */
+module Leaf(
+  input wire x
+);
+
+endmodule
+/*
+ * Generated by topstitch
+ * revision 1
+ */
+module Top;
+  Leaf inst (
+    .x(1'h0)
+  );
+endmodule
+"
+        );
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "Enum type mismatch in connection between Top.Consumer_i.shape[1:0] (enum type pkg::shape_t) and Top.Producer_i.color[1:0] (enum type pkg::rgb_t)"
+    )]
+    fn test_enum_type_mismatch() {
+        let input_verilog = "
+        package pkg;
+            typedef enum bit[1:0] {RED, GREEN, BLUE} rgb_t;
+            typedef enum bit[1:0] {CIRCLE, SQUARE, TRIANGLE} shape_t;
+        endpackage
+        module Producer import pkg::*; (
+            output rgb_t color
+        );
+        endmodule
+        module Consumer import pkg::*; (
+            input shape_t shape
+        );
+        endmodule
+        ";
+
+        let producer = ModDef::from_verilog("Producer", input_verilog, true, false);
+        let consumer = ModDef::from_verilog("Consumer", input_verilog, true, false);
+
+        let top = ModDef::new("Top");
+        let producer_inst = top.instantiate(&producer, None, None);
+        let consumer_inst = top.instantiate(&consumer, None, None);
+        producer_inst
+            .get_port("color")
+            .connect(&consumer_inst.get_port("shape"));
+
+        top.validate();
+    }
+
+    #[test]
+    fn test_instantiate_and_export_all() {
+        let leaf = ModDef::new("Leaf");
+        leaf.add_port("a", IO::Input(8));
+        leaf.add_port("b", IO::Output(4));
+        leaf.set_usage(Usage::EmitStubAndStop);
+
+        let top = ModDef::new("Top");
+        top.instantiate_and_export_all(&leaf, Some("leaf"));
+
+        assert_eq!(
+            top.emit(true),
+            "\
+module Leaf(
+  input wire [7:0] a,
+  output wire [3:0] b
+);
+
+endmodule
+module Top(
+  input wire [7:0] a,
+  output wire [3:0] b
+);
+  wire [7:0] leaf_a;
+  wire [3:0] leaf_b;
+  Leaf leaf (
+    .a(leaf_a),
+    .b(leaf_b)
+  );
+  assign leaf_a[7:0] = a[7:0];
+  assign b[3:0] = leaf_b[3:0];
+endmodule
+"
+        );
+    }
+
+    #[test]
+    fn test_set_enum_type() {
+        let leaf = ModDef::new("Leaf");
+        leaf.add_port("state", IO::Input(2));
+        leaf.add_port("next_state", IO::Output(2));
+        leaf.get_port("state").set_enum_type("my_pkg::state_t");
+        leaf.get_port("next_state").set_enum_type("my_pkg::state_t");
+        leaf.set_usage(Usage::EmitNothingAndStop);
+
+        let top = ModDef::new("Top");
+        let inst = top.instantiate(&leaf, Some("inst"), None);
+        inst.get_port("state").export();
+        inst.get_port("next_state").export();
+
+        assert_eq!(
+            top.emit(true),
+            "\
+module Top(
+  input wire [1:0] state,
+  output wire [1:0] next_state
+);
+  wire [1:0] inst_state;
+  wire [1:0] inst_next_state;
+  Leaf inst (
+    .state(my_pkg::state_t'(inst_state)),
+    .next_state(inst_next_state)
+  );
+  assign inst_state[1:0] = state[1:0];
+  assign next_state[1:0] = inst_next_state[1:0];
+endmodule
+"
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "set_enum_type() can only be called on module definition ports")]
+    fn test_set_enum_type_on_inst_port() {
+        let leaf = ModDef::new("Leaf");
+        leaf.add_port("state", IO::Input(2));
+
+        let top = ModDef::new("Top");
+        let inst = top.instantiate(&leaf, Some("inst"), None);
+        inst.get_port("state").set_enum_type("my_pkg::state_t");
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "Connection between Top.inst.state[1:0] (enum type my_pkg::state_t) and Top.state_in[1:0] (not enum-typed) requires set_allow_enum_mismatch(true)"
+    )]
+    fn test_enum_to_logic_connection() {
+        let leaf = ModDef::new("Leaf");
+        leaf.add_port("state", IO::Input(2));
+        leaf.get_port("state").set_enum_type("my_pkg::state_t");
+        leaf.set_usage(Usage::EmitNothingAndStop);
+
+        let top = ModDef::new("Top");
+        top.set_allow_enum_mismatch(false);
+        let state_in = top.add_port("state_in", IO::Input(2));
+        let inst = top.instantiate(&leaf, Some("inst"), None);
+        inst.get_port("state").connect(&state_in);
+
+        top.validate();
+    }
+
+    #[test]
+    fn test_enum_to_logic_connection_allowed() {
+        let leaf = ModDef::new("Leaf");
+        leaf.add_port("state", IO::Input(2));
+        leaf.get_port("state").set_enum_type("my_pkg::state_t");
+        leaf.set_usage(Usage::EmitNothingAndStop);
+
+        let top = ModDef::new("Top");
+        let state_in = top.add_port("state_in", IO::Input(2));
+        let inst = top.instantiate(&leaf, Some("inst"), None);
+        inst.get_port("state").connect(&state_in);
+
+        assert_eq!(
+            top.emit(true),
+            "\
+module Top(
+  input wire [1:0] state_in
+);
+  wire [1:0] inst_state;
+  Leaf inst (
+    .state(my_pkg::state_t'(inst_state))
+  );
+  assign inst_state[1:0] = state_in[1:0];
+endmodule
+"
+        );
+    }
+
+    #[test]
+    fn test_intf_assert_compatible_with() {
+        let a = ModDef::new("A");
+        a.add_port("a_data", IO::Output(8));
+        a.add_port("a_valid", IO::Output(1));
+        a.def_intf_from_prefix("intf", "a_");
+
+        let b = ModDef::new("B");
+        b.add_port("b_data", IO::Input(8));
+        b.add_port("b_valid", IO::Input(1));
+        b.def_intf_from_prefix("intf", "b_");
+
+        let top = ModDef::new("Top");
+        let a_inst = top.instantiate(&a, None, None);
+        let b_inst = top.instantiate(&b, None, None);
+        a_inst
+            .get_intf("intf")
+            .assert_compatible_with(&b_inst.get_intf("intf"));
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "Interfaces Top.A_i.intf and Top.B_i.intf are not compatible:
+  function 'data' has width 8 in Top.A_i.a_data[7:0] but width 4 in Top.B_i.b_data[3:0]
+  function 'valid' has incompatible directions: Top.A_i.a_valid[0:0] is Input(1) and Top.B_i.b_valid[0:0] is Input(1)
+  function 'last' is present in Top.A_i.intf but not in Top.B_i.intf
+  function 'ready' is present in Top.B_i.intf but not in Top.A_i.intf"
+    )]
+    fn test_intf_assert_compatible_with_mismatches() {
+        let a = ModDef::new("A");
+        a.add_port("a_data", IO::Output(8));
+        a.add_port("a_valid", IO::Input(1));
+        a.add_port("a_last", IO::Output(1));
+        a.def_intf_from_prefix("intf", "a_");
+
+        let b = ModDef::new("B");
+        b.add_port("b_data", IO::Input(4));
+        b.add_port("b_valid", IO::Input(1));
+        b.add_port("b_ready", IO::Output(1));
+        b.def_intf_from_prefix("intf", "b_");
+
+        let top = ModDef::new("Top");
+        let a_inst = top.instantiate(&a, None, None);
+        let b_inst = top.instantiate(&b, None, None);
+        a_inst
+            .get_intf("intf")
+            .assert_compatible_with(&b_inst.get_intf("intf"));
+    }
+
+    #[test]
+    fn test_emit_group_ports_by_direction() {
+        let top = ModDef::new("Top");
+        let a = top.add_port("a", IO::Input(8));
+        let y = top.add_port("y", IO::Output(8));
+        let b = top.add_port("b", IO::Input(1));
+        let z = top.add_port("z", IO::Output(1));
+        y.connect(&a);
+        z.connect(&b);
+
+        assert_eq!(
+            top.emit_with_options(
+                true,
+                &EmitOptions {
+                    group_ports_by_direction: true,
+                }
+            ),
+            "\
+module Top(
+  // inputs
+  input wire [7:0] a,
+  input wire b,
+  // outputs
+  output wire [7:0] y,
+  output wire z
+);
+  assign y[7:0] = a[7:0];
+  assign z = b;
+endmodule
+"
+        );
+    }
+
+    #[test]
+    fn test_get_connectivity_matrix() {
+        let leaf = ModDef::new("Leaf");
+        leaf.add_port("i", IO::Input(2));
+        leaf.add_port("o", IO::Output(2));
+
+        let top = ModDef::new("Top");
+        let a = top.add_port("a", IO::Input(2));
+        let y = top.add_port("y", IO::Output(1));
+        let inst = top.instantiate(&leaf, Some("inst"), None);
+        inst.get_port("i").connect(&a);
+        y.connect(&inst.get_port("o").bit(1));
+
+        let a_key = PortKey::ModDefPort {
+            mod_def_name: "Top".to_string(),
+            port_name: "a".to_string(),
+        };
+        let y_key = PortKey::ModDefPort {
+            mod_def_name: "Top".to_string(),
+            port_name: "y".to_string(),
+        };
+        let i_key = PortKey::ModInstPort {
+            mod_def_name: "Top".to_string(),
+            inst_name: "inst".to_string(),
+            port_name: "i".to_string(),
+        };
+        let o_key = PortKey::ModInstPort {
+            mod_def_name: "Top".to_string(),
+            inst_name: "inst".to_string(),
+            port_name: "o".to_string(),
+        };
+
+        assert_eq!(
+            top.get_connectivity_matrix(),
+            vec![
+                (a_key.clone(), 0, i_key.clone(), 0),
+                (a_key, 1, i_key, 1),
+                (o_key, 1, y_key, 0),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_has_generated_verilog_and_verilog_import() {
+        let input_verilog = "
+        module Leaf #(
+            parameter W = 8
+        ) (
+            input [W-1:0] a
+        );
+        endmodule
+        ";
+
+        let leaf = ModDef::from_verilog("Leaf", input_verilog, true, false);
+        assert!(leaf.has_verilog_import());
+        assert!(!leaf.has_generated_verilog());
+
+        let leaf_16 = leaf.parameterize(&[("W", 16)], None, None);
+        assert!(!leaf_16.has_verilog_import());
+        assert!(leaf_16.has_generated_verilog());
+
+        let top = ModDef::new("Top");
+        assert!(!top.has_verilog_import());
+        assert!(!top.has_generated_verilog());
+    }
+
+    #[test]
+    fn test_port_slice_get_mod_def_name() {
+        let leaf = ModDef::new("Leaf");
+        leaf.add_port("y", IO::Output(8));
+
+        let top = ModDef::new("Top");
+        top.add_port("a", IO::Input(8));
+        let inst = top.instantiate(&leaf, Some("inst"), None);
+
+        assert_eq!(leaf.get_port("y").slice(3, 0).get_mod_def_name(), "Leaf");
+        assert_eq!(top.get_port("a").slice(3, 0).get_mod_def_name(), "Top");
+        assert_eq!(inst.get_port("y").slice(3, 0).get_mod_def_name(), "Leaf");
+    }
+
+    #[test]
+    fn test_import_parameters_from_verilog() {
+        let input_verilog = "
+        module Leaf #(
+            parameter int WIDTH = 8,
+            parameter logic [3:0] MODE = 4'hA,
+            parameter DEPTH = WIDTH * 2
+        ) (
+            input [WIDTH-1:0] a
+        );
+        endmodule
+        ";
+
+        let leaf = ModDef::from_verilog("Leaf", input_verilog, true, false);
+        assert!(leaf.has_parameter("WIDTH"));
+        assert!(leaf.has_parameter("DEPTH"));
+        assert!(!leaf.has_parameter("OTHER"));
+        assert_eq!(leaf.get_default_parameter_value("WIDTH"), Some(8u32.into()));
+        assert_eq!(leaf.get_default_parameter_value("MODE"), Some(10u32.into()));
+        assert_eq!(leaf.get_default_parameter_value("DEPTH"), None);
+        assert_eq!(leaf.get_default_parameter_value("OTHER"), None);
+
+        let other = ModDef::new("Leaf");
+        other.import_parameters_from_verilog(input_verilog);
+        assert_eq!(
+            other.get_default_parameter_value("WIDTH"),
+            Some(8u32.into())
+        );
+
+        // Imported parameters are not emitted when stubbing.
+        leaf.set_usage(Usage::EmitStubAndStop);
+        assert_eq!(
+            leaf.emit(false),
+            "\
+module Leaf(
+  input wire [7:0] a
+);
+
+endmodule
+"
+        );
+    }
+
+    #[test]
+    fn test_swap_port_direction() {
+        let leaf = ModDef::new("Leaf");
+        leaf.add_port("a", IO::Input(8));
+        leaf.add_port("b", IO::Output(4));
+        leaf.add_port("c", IO::InOut(2));
+
+        leaf.swap_port_direction("a");
+        leaf.swap_port_direction("b");
+        leaf.swap_port_direction("c");
+
+        assert_eq!(leaf.get_port("a").io(), IO::Output(8));
+        assert_eq!(leaf.get_port("b").io(), IO::Input(4));
+        assert_eq!(leaf.get_port("c").io(), IO::InOut(2));
+    }
+
+    #[test]
+    #[should_panic(expected = "Cannot swap the direction of port Top.a: it is already connected")]
+    fn test_swap_port_direction_connected() {
+        let top = ModDef::new("Top");
+        let a = top.add_port("a", IO::Input(8));
+        let y = top.add_port("y", IO::Output(8));
+        y.connect(&a);
+
+        top.swap_port_direction("a");
+    }
+
+    #[test]
+    fn test_connect_permuted() {
+        let top = ModDef::new("Top");
+        let a = top.add_port("a", IO::Input(3));
+        let y = top.add_port("y", IO::Output(3));
+        y.connect_permuted(&a, &[2, 0, 1]);
+
+        assert_eq!(
+            top.emit(true),
+            "\
+module Top(
+  input wire [2:0] a,
+  output wire [2:0] y
+);
+  assign y[0:0] = a[2:2];
+  assign y[1:1] = a[0:0];
+  assign y[2:2] = a[1:1];
+endmodule
+"
+        );
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "Permutation for connecting Top.y[2:0] to Top.a[2:0] contains index 0 more than once"
+    )]
+    fn test_connect_permuted_not_bijection() {
+        let top = ModDef::new("Top");
+        let a = top.add_port("a", IO::Input(3));
+        let y = top.add_port("y", IO::Output(3));
+        y.connect_permuted(&a, &[0, 1, 0]);
+    }
+
+    #[test]
+    fn test_emit_json() {
+        let leaf = ModDef::new("Leaf");
+        leaf.add_port("x", IO::Input(8));
+        leaf.add_port("y", IO::Output(4));
+
+        let top = ModDef::new("Top");
+        let a = top.add_port("a", IO::Input(8));
+        let inst = top.instantiate(&leaf, Some("inst"), None);
+        inst.get_port("x").connect(&a);
+        inst.get_port("y").unused();
+
+        assert_eq!(
+            top.emit_json(),
+            r#"{
+  "name": "Top",
+  "ports": [
+    {
+      "name": "a",
+      "direction": "input",
+      "width": 8
+    }
+  ],
+  "instances": [
+    {
+      "name": "inst",
+      "module": "Leaf"
+    }
+  ],
+  "connections": [
+    {
+      "lhs": "inst.x[7:0]",
+      "rhs": "a[7:0]"
+    }
+  ],
+  "inst_connections": [],
+  "tieoffs": [],
+  "unused": [
+    "inst.y[3:0]"
+  ]
+}"#
+        );
+    }
+
+    #[test]
+    fn test_instance_comment() {
+        let leaf = ModDef::new("Leaf");
+        leaf.add_port("x", IO::Input(8));
+        leaf.add_parameter("N", "int", "1");
+        leaf.set_usage(Usage::EmitNothingAndStop);
+
+        let top = ModDef::new("Top");
+        let inst = top.instantiate(&leaf, Some("inst"), None);
+        inst.get_port("x").export();
+        inst.set_comment("Created by generator.rs:42\nfor testing");
+        inst.set_parameter("N", "2");
+
+        assert_eq!(
+            top.emit(true),
+            "\
+module Top(
+  input wire [7:0] x
+);
+  wire [7:0] inst_x;
+  // Created by generator.rs:42
+  // for testing
+  Leaf #(
+    .N(2)
+  ) inst (
+    .x(inst_x)
+  );
+  assign inst_x[7:0] = x[7:0];
+endmodule
+"
+        );
+    }
+
+    #[test]
+    fn test_merge_connections() {
+        let top = ModDef::new("Top");
+        let a = top.add_port("a", IO::Input(8));
+        let y = top.add_port("y", IO::Output(6));
+        let z = top.add_port("z", IO::Output(2));
+        for i in 0..4 {
+            y.bit(i).connect(&a.bit(i + 4));
+        }
+        // Not adjacent on the driver side, so not merged
+        y.bit(4).connect(&a.bit(0));
+        y.bit(5).connect(&a.bit(2));
+        z.bit(0).connect(&a.bit(1));
+        z.bit(1).connect(&a.bit(3));
+
+        y.merge_connections();
+
+        assert_eq!(
+            top.emit(true),
+            "\
+module Top(
+  input wire [7:0] a,
+  output wire [5:0] y,
+  output wire [1:0] z
+);
+  assign y[3:0] = a[7:4];
+  assign y[4:4] = a[0:0];
+  assign y[5:5] = a[2:2];
+  assign z[0:0] = a[1:1];
+  assign z[1:1] = a[3:3];
+endmodule
+"
+        );
+    }
+
+    #[test]
+    fn test_get_port_ci() {
+        let top = ModDef::new("Top");
+        top.add_port("clk", IO::Input(1));
+        top.add_port("Data_In", IO::Input(8));
+
+        assert_eq!(top.get_port_ci("CLK").name(), "clk");
+        assert_eq!(top.get_port_ci("data_in").name(), "Data_In");
+    }
+
+    #[test]
+    #[should_panic(expected = "Case-insensitive match for port Top.CLK is ambiguous: clk, Clk")]
+    fn test_get_port_ci_ambiguous() {
+        let top = ModDef::new("Top");
+        top.add_port("clk", IO::Input(1));
+        top.add_port("Clk", IO::Input(1));
+
+        top.get_port_ci("CLK");
+    }
+
+    #[test]
+    fn test_find_signal_paths() {
+        let src = ModDef::new("Src");
+        src.add_port("o1", IO::Output(1));
+        src.add_port("o2", IO::Output(1));
+        src.add_port("o3", IO::Output(1));
+
+        let mid = ModDef::new("Mid");
+        mid.add_port("i", IO::Input(1));
+        mid.add_port("o", IO::Output(1));
+
+        let dst = ModDef::new("Dst");
+        dst.add_port("i1", IO::Input(1));
+        dst.add_port("i2", IO::Input(1));
+        dst.add_port("i3", IO::Input(1));
+
+        let top = ModDef::new("Top");
+        let a = top.instantiate(&src, Some("a"), None);
+        let b = top.instantiate(&mid, Some("b"), None);
+        let c = top.instantiate(&mid, Some("c"), None);
+        let d = top.instantiate(&dst, Some("d"), None);
+
+        a.get_port("o1").connect(&b.get_port("i"));
+        b.get_port("o").connect(&c.get_port("i"));
+        c.get_port("o").connect(&d.get_port("i1"));
+        a.get_port("o2").connect_to_net("shortcut");
+        d.get_port("i2").connect_to_net("shortcut");
+        a.get_port("o3").connect(&d.get_port("i3"));
+
+        assert_eq!(
+            top.find_signal_paths(&a, &d),
+            vec![
+                vec!["a_o3".to_string()],
+                vec!["shortcut".to_string()],
+                vec!["a_o1".to_string(), "b_o".to_string(), "c_o".to_string()],
+            ]
+        );
+        assert!(top.find_signal_paths(&d, &a).is_empty());
+    }
+
+    #[test]
+    fn test_get_max_pipeline_depth_to_output() {
+        let pipeline = |depth| PipelineConfig {
+            clk: "clk".to_string(),
+            depth,
+        };
+
+        let mid = ModDef::new("Mid");
+        mid.add_port("clk", IO::Input(1));
+        let i = mid.add_port("i", IO::Input(8));
+        let o = mid.add_port("o", IO::Output(8));
+        o.connect_pipeline(&i, pipeline(3));
+
+        let leaf = ModDef::new("Leaf");
+        leaf.add_port("i", IO::Input(8));
+        leaf.add_port("o", IO::Output(8));
+
+        let top = ModDef::new("Top");
+        top.add_port("clk", IO::Input(1));
+        let a = top.add_port("a", IO::Input(8));
+        let b = top.add_port("b", IO::Input(8));
+        let y = top.add_port("y", IO::Output(8));
+        let z = top.add_port("z", IO::Output(8));
+        let w = top.add_port("w", IO::Output(8));
+        top.add_port("unreachable", IO::Output(8));
+
+        let mid_inst = top.instantiate(&mid, Some("mid"), None);
+        mid_inst.get_port("i").connect_pipeline(&a, pipeline(2));
+        y.connect_pipeline(&mid_inst.get_port("o"), pipeline(1));
+
+        let leaf_inst = top.instantiate(&leaf, Some("leaf"), None);
+        leaf_inst.get_port("i").connect(&b);
+        z.connect_pipeline(&leaf_inst.get_port("o"), pipeline(4));
+
+        w.connect(&a);
+
+        assert_eq!(top.get_max_pipeline_depth_to_output("y"), 6);
+        assert_eq!(top.get_max_pipeline_depth_to_output("z"), 4);
+        assert_eq!(top.get_max_pipeline_depth_to_output("w"), 0);
+        assert_eq!(top.get_max_pipeline_depth_to_output("unreachable"), 0);
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "Top.src.o[7:0] cannot drive, since Top.src.o is a module instance input. Connections to Top.src.o:
+  Top.src.o[7:0] drives Top.y[7:0]
+  Top.src.o[7:0] drives Top.dst.i[7:0]"
+    )]
+    fn test_inconsistent_direction_message() {
+        let src = ModDef::new("Src");
+        src.add_port("o", IO::Output(8));
+        src.set_usage(Usage::EmitNothingAndStop);
+
+        let dst = ModDef::new("Dst");
+        dst.add_port("i", IO::Input(8));
+        dst.set_usage(Usage::EmitNothingAndStop);
+
+        let top = ModDef::new("Top");
+        let y = top.add_port("y", IO::Output(8));
+        let src_inst = top.instantiate(&src, Some("src"), None);
+        let dst_inst = top.instantiate(&dst, Some("dst"), None);
+        y.connect(&src_inst.get_port("o"));
+        dst_inst.get_port("i").connect(&src_inst.get_port("o"));
+
+        // The direction changes after the instance port has been connected
+        src.swap_port_direction("o");
+
+        top.validate();
+    }
+
+    #[test]
+    fn test_intf_split_by_direction() {
+        let leaf = ModDef::new("Leaf");
+        leaf.add_port("bus_req", IO::Input(8));
+        leaf.add_port("bus_rsp", IO::Output(8));
+        leaf.add_port("bus_valid", IO::Input(1));
+        leaf.add_port("bus_ready", IO::Output(1));
+        let bus = leaf.def_intf_from_prefix("bus", "bus_");
+
+        bus.split_by_direction();
+
+        let top = ModDef::new("Top");
+        let inst = top.instantiate(&leaf, Some("inst"), None);
+        inst.get_intf("bus_inputs")
+            .export_with_prefix("in_intf", "in_");
+        inst.get_intf("bus_outputs")
+            .export_with_prefix("out_intf", "out_");
+        assert!(top.has_port("in_req"));
+        assert!(top.has_port("in_valid"));
+        assert!(top.has_port("out_rsp"));
+        assert!(top.has_port("out_ready"));
+        assert!(!top.has_port("in_rsp"));
+        assert!(!top.has_port("out_req"));
+    }
+
+    #[test]
+    fn test_get_port_by_index() {
+        let a = ModDef::new("A");
+        a.add_port("x", IO::Input(8));
+        a.add_port("y", IO::Output(4));
+        a.add_port("z", IO::InOut(1));
+
+        assert_eq!(a.get_port_count(), 3);
+        let names: Vec<String> = (0..a.get_port_count())
+            .map(|i| a.get_port_by_index(i).unwrap().name().to_string())
+            .collect();
+        assert_eq!(names, vec!["x", "y", "z"]);
+        assert!(a.get_port_by_index(3).is_none());
+    }
+
+    #[test]
+    #[should_panic(expected = "Cannot connect a port slice to itself: A.x[3:0]")]
+    fn test_connect_slice_to_itself() {
+        let a = ModDef::new("A");
+        let x = a.add_port("x", IO::Input(8));
+        x.slice(3, 0).connect(&x.slice(3, 0));
+    }
+
+    #[test]
+    fn test_check_combinational_loops() {
+        let feed = ModDef::new("Feed");
+        feed.add_port("in", IO::Input(8));
+        feed.add_port("out", IO::Output(8));
+        feed.get_port("in").connect(&feed.get_port("out"));
+
+        let top = ModDef::new("Top");
+        let a = top.instantiate(&feed, Some("a"), None);
+        let b = top.instantiate(&feed, Some("b"), None);
+        a.get_port("out").connect(&b.get_port("in"));
+        b.get_port("out").connect(&a.get_port("in"));
+
+        assert_eq!(
+            top.check_combinational_loops(),
+            Err(vec![vec![
+                "Top.a.out[0]".to_string(),
+                "Top.b.in[0]".to_string(),
+                "Top.b.out[0]".to_string(),
+                "Top.a.in[0]".to_string(),
+            ]])
+        );
+    }
+
+    #[test]
+    fn test_check_combinational_loops_pipelined() {
+        let feed = ModDef::new("Feed");
+        feed.add_port("clk", IO::Input(1));
+        feed.add_port("in", IO::Input(8));
+        feed.add_port("out", IO::Output(8));
+        feed.get_port("in").connect_pipeline(
+            &feed.get_port("out"),
+            PipelineConfig {
+                clk: "clk".to_string(),
+                depth: 1,
+            },
+        );
+
+        let top = ModDef::new("Top");
+        let a = top.instantiate(&feed, Some("a"), None);
+        let b = top.instantiate(&feed, Some("b"), None);
+        a.get_port("out").connect(&b.get_port("in"));
+        b.get_port("out").connect(&a.get_port("in"));
+
+        assert_eq!(top.check_combinational_loops(), Ok(()));
+    }
+
+    #[test]
+    fn test_from_systemverilog_interface() {
+        let (bus, bus_intf) = ModDef::from_systemverilog_interface(
+            "
+interface bus_if;
+  logic [7:0] req;
+  logic [15:0] rsp;
+  logic valid;
+  modport host (output req, valid, input rsp);
+  modport device (input req, valid, output rsp);
+endinterface
+",
+        );
+
+        assert_eq!(
+            bus.emit(true),
+            "\
+module bus_if(
+  output wire [7:0] req,
+  output wire valid,
+  input wire [15:0] rsp
+);
+
+endmodule
+"
+        );
+        assert_eq!(bus_intf.directionality(), IntfDirectionality::Mixed);
+
+        let top = ModDef::new("Top");
+        let host = top.instantiate(&bus, Some("host"), None);
+        host.get_intf("bus_if").export_with_prefix("bus", "bus_");
+        assert!(top.has_port("bus_req"));
+        assert!(top.has_port("bus_valid"));
+        assert!(top.has_port("bus_rsp"));
+    }
+
+    #[test]
+    #[should_panic(expected = "Recursive instantiation of A (via A)")]
+    fn test_instantiate_self() {
+        let a = ModDef::new("A");
+        a.instantiate(&a, Some("a_i"), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "Recursive instantiation of A (via B)")]
+    fn test_instantiate_recursive() {
+        let a = ModDef::new("A");
+        let b = ModDef::new("B");
+        let c = ModDef::new("C");
+        b.instantiate(&c, Some("c_i"), None);
+        c.instantiate(&a, Some("a_i"), None);
+        a.instantiate(&b, Some("b_i"), None);
+    }
+
+    #[test]
+    fn test_get_driven_by() {
+        let src = ModDef::new("Src");
+        src.add_port("out", IO::Output(8));
+        let other = ModDef::new("Other");
+        other.add_port("out", IO::Output(1));
+        let sink = ModDef::new("Sink");
+        sink.add_port("a", IO::Input(8));
+        sink.add_port("b", IO::Input(8));
+        sink.add_port("c", IO::Input(1));
+        sink.add_port("d", IO::Input(1));
+
+        let top = ModDef::new("Top");
+        top.add_port("d", IO::Input(1));
+        let src0 = top.instantiate(&src, Some("src0"), None);
+        let src1 = top.instantiate(&src, Some("src1"), None);
+        let other_i = top.instantiate(&other, Some("other_i"), None);
+        let sink_i = top.instantiate(&sink, Some("sink_i"), None);
+        src0.get_port("out").connect(&sink_i.get_port("a"));
+        src1.get_port("out").connect(&sink_i.get_port("b"));
+        other_i.get_port("out").connect_to_net("net_c");
+        sink_i.get_port("c").connect_to_net("net_c");
+        top.get_port("d").connect(&sink_i.get_port("d"));
+
+        assert_eq!(top.get_driven_by("sink_i"), vec!["Src", "Other"]);
+        assert!(top.get_driven_by("src0").is_empty());
+    }
+
+    #[test]
+    fn test_intf_directionality() {
+        let a = ModDef::new("A");
+        a.add_port("in_data", IO::Input(8));
+        a.add_port("in_valid", IO::Input(1));
+        a.add_port("out_data", IO::Output(8));
+        a.add_port("io_pad", IO::InOut(1));
+        a.add_port("io_en", IO::Output(1));
+
+        let inputs = a.def_intf_from_prefix("inputs", "in_");
+        let outputs = a.def_intf_from_prefix("outputs", "out_");
+        let mixed = a.def_intf_from_regex("data", "^(in|out)_data$", "${1}");
+        let bidir = a.def_intf_from_prefix("io", "io_");
+
+        assert_eq!(inputs.directionality(), IntfDirectionality::AllInput);
+        assert_eq!(outputs.directionality(), IntfDirectionality::AllOutput);
+        assert_eq!(mixed.directionality(), IntfDirectionality::Mixed);
+        assert_eq!(bidir.directionality(), IntfDirectionality::Bidirectional);
+
+        let top = ModDef::new("Top");
+        let a_inst = top.instantiate(&a, Some("a_inst"), None);
+        assert_eq!(
+            a_inst.get_intf("inputs").directionality(),
+            IntfDirectionality::AllInput
+        );
+    }
+
+    #[test]
+    fn test_connect_to_self() {
+        let a = ModDef::new("A");
+        let a_in = a.add_port("a_in", IO::Input(8));
+        let a_out = a.add_port("a_out", IO::Output(8));
+        a_out.connect_to_self(&a_in);
+
+        assert_eq!(
+            a.emit(true),
+            "\
+module A(
+  input wire [7:0] a_in,
+  output wire [7:0] a_out
+);
+  assign a_out[7:0] = a_in[7:0];
+endmodule
+"
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Cannot create loopback from A.a_in: it is not an output")]
+    fn test_connect_to_self_wrong_direction() {
+        let a = ModDef::new("A");
+        let a_in = a.add_port("a_in", IO::Input(8));
+        let a_out = a.add_port("a_out", IO::Output(8));
+        a_in.connect_to_self(&a_out);
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "Cannot create loopback between A.a_out and B.b_in: they are on different module definitions"
+    )]
+    fn test_connect_to_self_different_mod_defs() {
+        let a = ModDef::new("A");
+        let a_out = a.add_port("a_out", IO::Output(8));
+        let b = ModDef::new("B");
+        let b_in = b.add_port("b_in", IO::Input(8));
+        a_out.connect_to_self(&b_in);
+    }
+
+    #[test]
+    fn test_register_boundary() {
+        let leaf = ModDef::new("Leaf");
+        leaf.add_port("clk", IO::Input(1));
+        leaf.add_port("x", IO::Input(8));
+        leaf.add_port("y", IO::Output(8));
+
+        let top = ModDef::new("Top");
+        top.add_port("clk", IO::Input(1));
+        top.add_port("a", IO::Input(8));
+        top.add_port("b", IO::Output(8));
+        top.add_port("c", IO::Input(4));
+        top.add_port("d", IO::Output(4));
+        top.add_port("e", IO::Output(1));
+        let leaf_i = top.instantiate(&leaf, Some("leaf_i"), None);
+        top.get_port("clk").connect(&leaf_i.get_port("clk"));
+        top.get_port("a").connect(&leaf_i.get_port("x"));
+        top.get_port("b").connect(&leaf_i.get_port("y"));
+        top.get_port("c").connect(&top.get_port("d"));
+        top.get_port("e").tieoff(0);
+
+        let registered: Vec<String> = top
+            .register_boundary("clk", 1)
+            .iter()
+            .map(|port| port.name().to_string())
+            .collect();
+        assert_eq!(registered, vec!["a", "b", "c", "d"]);
+
+        assert_eq!(top.get_max_pipeline_depth_to_output("b"), 2);
+        assert_eq!(top.get_max_pipeline_depth_to_output("d"), 2);
+    }
+
+    #[test]
+    fn test_emit_port_list_header() {
+        let leaf = ModDef::new("Leaf");
+        leaf.add_port("x", IO::Input(8));
+        leaf.add_port("y", IO::Output(8));
+        leaf.get_port("x").connect(&leaf.get_port("y"));
+
+        let top = ModDef::new("Top");
+        top.add_port("a", IO::Input(8));
+        top.add_port("b", IO::Output(8));
+        top.add_port("c", IO::Input(1));
+        top.get_port("c").unused();
+        let leaf_i = top.instantiate(&leaf, Some("leaf_i"), None);
+        top.get_port("a").connect(&leaf_i.get_port("x"));
+        top.get_port("b").connect(&leaf_i.get_port("y"));
+
+        assert_eq!(
+            top.emit_port_list_header(),
+            "\
+module Top(
+  input wire [7:0] a,
+  output wire [7:0] b,
+  input wire c
+);
+"
+        );
+    }
+
+    #[test]
+    fn test_canonicalize_port_order_to_match() {
+        let leaf = ModDef::new("Leaf");
+        leaf.add_port("a", IO::Input(8));
+        leaf.add_port("b", IO::Output(8));
+        leaf.add_port("c", IO::Input(1));
+
+        let wrapper = ModDef::new("Wrapper");
+        wrapper.add_port("extra", IO::Input(1));
+        wrapper.add_port("c", IO::Input(1));
+        wrapper.add_port("b", IO::Output(8));
+        wrapper.add_port("a", IO::Input(8));
+        wrapper.get_port("extra").unused();
+        let leaf_i = wrapper.instantiate(&leaf, Some("leaf_i"), None);
+        for name in ["a", "b", "c"] {
+            wrapper.get_port(name).connect(&leaf_i.get_port(name));
+        }
+
+        wrapper.canonicalize_port_order_to_match(&leaf);
+
+        assert_eq!(
+            wrapper.emit_port_list_header(),
+            "\
+module Wrapper(
+  input wire [7:0] a,
+  output wire [7:0] b,
+  input wire c,
+  input wire extra
+);
+"
+        );
+    }
+
+    #[test]
+    fn test_omit_pipeline_out_stages() {
+        let a = ModDef::new("A");
+        a.add_port("clk", IO::Input(1));
+        let a_in = a.add_port("a_in", IO::Input(8));
+        let a_out = a.add_port("a_out", IO::Output(8));
+        a_in.connect_pipeline(
+            &a_out,
+            PipelineConfig {
+                clk: "clk".to_string(),
+                depth: 2,
+            },
+        );
+        a.set_omit_pipeline_out_stages(true);
+
+        let verilog = a.emit(true);
+        assert!(verilog.contains("br_delay_nr"));
+        assert!(!verilog.contains("out_stages"));
+    }
+
+    #[test]
+    fn test_emit_sdc_unconstrained_clock() {
+        let module = ModDef::new("Module");
+        module.add_port("clk", IO::Input(1));
+        module.add_port("ref_clk", IO::Input(1));
+        module.add_port("a", IO::Input(8));
+        module.set_port_timing_constraint("ref_clk", 10.0);
+
+        assert_eq!(
+            module.emit_sdc(),
+            "\
+create_clock -name ref_clk -period 10 [get_ports ref_clk]
+set_input_delay 0 -clock ref_clk [get_ports a]
+"
+        );
+    }
+
+    #[test]
+    fn test_wrap_enum_ports() {
+        let leaf = ModDef::new("Leaf");
+        leaf.add_port("state", IO::Input(2));
+        leaf.get_port("state").set_enum_type("my_pkg::state_t");
+        leaf.set_usage(Usage::EmitNothingAndStop);
+
+        // Wrapper ports are plain, so a parent of the wrapper does not cast.
+        let wrapper = leaf.wrap(None, None);
+        wrapper.set_allow_enum_mismatch(false);
+        wrapper.validate();
+        wrapper.set_usage(Usage::EmitNothingAndStop);
+
+        let top = ModDef::new("Top");
+        top.set_allow_enum_mismatch(false);
+        let wrapper_i = top.instantiate(&wrapper, Some("wrapper_i"), None);
+        wrapper_i.get_port("state").export();
+
+        assert_eq!(
+            top.emit(true),
+            "\
+module Top(
+  input wire [1:0] state
+);
+  wire [1:0] wrapper_i_state;
+  Leaf_wrapper wrapper_i (
+    .state(wrapper_i_state)
+  );
+  assign wrapper_i_state[1:0] = state[1:0];
+endmodule
+"
+        );
+    }
+
+    #[test]
+    fn test_get_max_pipeline_depth_to_output_long_chain() {
+        let stage = ModDef::new("Stage");
+        stage.add_port("valid_in", IO::Input(1));
+        stage.add_port("data_in", IO::Input(8));
+        stage.add_port("valid_out", IO::Output(1));
+        stage.add_port("data_out", IO::Output(8));
+
+        let top = ModDef::new("Top");
+        top.add_port("clk", IO::Input(1));
+        let valid_in = top.add_port("valid_in", IO::Input(1));
+        let data_in = top.add_port("data_in", IO::Input(8));
+        let valid_out = top.add_port("valid_out", IO::Output(1));
+        let data_out = top.add_port("data_out", IO::Output(8));
+
+        // Each stage is opaque, so both of its outputs depend on both of its
+        // inputs, and the number of paths through the chain doubles with each
+        // stage.
+        let stages = top.instantiate_array(&stage, &[40], None, None);
+        stages[0].get_port("valid_in").connect(&valid_in);
+        stages[0].get_port("data_in").connect(&data_in);
+        for (prev, next) in stages.iter().zip(stages.iter().skip(1)) {
+            for (input, output) in [("valid_in", "valid_out"), ("data_in", "data_out")] {
+                next.get_port(input).connect_pipeline(
+                    &prev.get_port(output),
+                    PipelineConfig {
+                        clk: "clk".to_string(),
+                        depth: 1,
+                    },
+                );
+            }
+        }
+        valid_out.connect(&stages[39].get_port("valid_out"));
+        data_out.connect(&stages[39].get_port("data_out"));
+
+        assert_eq!(top.get_max_pipeline_depth_to_output("valid_out"), 39);
+        assert_eq!(top.get_max_pipeline_depth_to_output("data_out"), 39);
+    }
+
+    #[test]
+    fn test_get_hierarchy_depth_replicated() {
+        // Each level instantiates the level below it twice, so there are 2^40
+        // instance paths to the bottom of the hierarchy.
+        let mut level = ModDef::new("Level0");
+        for i in 1..=40 {
+            let next = ModDef::new(format!("Level{}", i));
+            next.instantiate(&level, Some("a"), None);
+            next.instantiate(&level, Some("b"), None);
+            level = next;
+        }
+        assert_eq!(level.get_hierarchy_depth(), 40);
+    }
+
+    #[test]
+    fn test_instantiate_and_export_all_collision() {
+        let leaf = ModDef::new("Leaf");
+        leaf.add_port("a", IO::Input(8));
+        leaf.add_port("b", IO::Output(4));
+
+        let top = ModDef::new("Top");
+        top.add_port("b", IO::Output(4));
+
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            top.instantiate_and_export_all(&leaf, Some("leaf"));
+        }));
+        assert!(result.is_err());
+
+        // Nothing was added before the name collision was detected.
+        assert!(top.get_instances().is_empty());
+        assert!(!top.has_port("a"));
+    }
+
+    #[test]
+    fn test_register_boundary_fanout() {
+        let top = ModDef::new("Top");
+        top.add_port("clk", IO::Input(1));
+        let a = top.add_port("a", IO::Input(8));
+        let b = top.add_port("b", IO::Output(8));
+        let c = top.add_port("c", IO::Output(8));
+        b.connect(&a);
+        c.connect(&a);
+
+        top.register_boundary("clk", 1);
+
+        // Registers are added per connection, so each of the two loads of "a"
+        // gets its own pipeline.
+        let verilog = top.emit(true);
+        assert_eq!(verilog.matches("br_delay_nr #(").count(), 2);
+        assert_eq!(top.get_max_pipeline_depth_to_output("b"), 2);
+        assert_eq!(top.get_max_pipeline_depth_to_output("c"), 2);
+    }
+
+    #[test]
+    fn test_register_boundary_clock_mismatch() {
+        let top = ModDef::new("Top");
+        top.add_port("clk", IO::Input(1));
+        top.add_port("clk2", IO::Input(1));
+        let a = top.add_port("a", IO::Input(8));
+        let b = top.add_port("b", IO::Output(8));
+        let c = top.add_port("c", IO::Input(8));
+        let d = top.add_port("d", IO::Output(8));
+        b.connect(&a);
+        d.connect_pipeline(
+            &c,
+            PipelineConfig {
+                clk: "clk2".to_string(),
+                depth: 1,
+            },
+        );
+
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            top.register_boundary("clk", 1);
+        }));
+        assert!(result.is_err());
+
+        // The connection checked before the mismatch was not modified.
+        assert_eq!(top.get_max_pipeline_depth_to_output("b"), 0);
+        assert_eq!(top.get_max_pipeline_depth_to_output("d"), 1);
+    }
 }